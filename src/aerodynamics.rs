@@ -0,0 +1,134 @@
+use std::float;
+use std::num::{Zero, One};
+use nalgebra::traits::division_ring::DivisionRing;
+use nalgebra::traits::norm::Norm;
+use nalgebra::traits::dot::Dot;
+use nalgebra::traits::cross::Cross;
+use nalgebra::traits::vector_space::VectorSpace;
+use soft_body::PointMass;
+
+/// Velocity of the air.
+pub enum Wind<N, V>
+{
+  /// Uniform and constant wind.
+  ConstantWind(V),
+  /// Wind of mean velocity `V` whose speed fluctuates by up to `amplitude` (relative to the mean
+  /// speed), with about `frequency` gusts per second travelling with the wind.
+  GustingWind(V, N, N),
+  /// Wind velocity as a function of the position and the time.
+  WindField(@fn(&V, N) -> V)
+}
+
+impl<N: DivisionRing + NumCast + Trigonometric + Clone, V: VectorSpace<N> + Norm<N> + Dot<N> + Clone>
+    Wind<N, V>
+{
+  pub fn velocity(&self, position: &V, t: &N) -> V
+  {
+    match *self
+    {
+      ConstantWind(ref v) => v.clone(),
+      GustingWind(ref v, ref amplitude, ref frequency) =>
+      {
+        let sqspeed = v.sqnorm();
+
+        if sqspeed.is_zero()
+        { return v.clone() }
+
+        // a gust reaches the points downwind later
+        let tau   = *t - position.dot(v) / sqspeed;
+        let w     = NumCast::from::<N, float>(2.0 * float::consts::pi) * *frequency * tau;
+        let noise = (w.sin() +
+                     (w * NumCast::from::<N, float>(2.3) + NumCast::from::<N, float>(1.7)).sin() * NumCast::from::<N, float>(0.5) +
+                     (w * NumCast::from::<N, float>(4.1) + NumCast::from::<N, float>(0.3)).sin() * NumCast::from::<N, float>(0.25)) /
+                    NumCast::from::<N, float>(1.75);
+
+        v.scalar_mul(&(One::one::<N>() + *amplitude * noise))
+      },
+      WindField(f) => f(position, t.clone())
+    }
+  }
+}
+
+/// Aerodynamic drag and lift on the triangles of a surface, due to their velocity relative to
+/// the wind.
+pub struct Aerodynamics<N, V>
+{
+  wind:             Wind<N, V>,
+  /// Density of the air.
+  air_density:      N,
+  drag_coefficient: N,
+  lift_coefficient: N
+}
+
+impl<N: DivisionRing + NumCast + Trigonometric + Ord + Clone,
+     V: VectorSpace<N> + Norm<N> + Dot<N> + Cross<V> + Clone>
+    Aerodynamics<N, V>
+{
+  pub fn new(wind: Wind<N, V>) -> Aerodynamics<N, V>
+  {
+    Aerodynamics {
+      wind:             wind,
+      air_density:      NumCast::from::<N, float>(1.2),
+      drag_coefficient: One::one(),
+      lift_coefficient: NumCast::from::<N, float>(0.5)
+    }
+  }
+
+  /// Adds the force applied on each triangle at the time `t` to the forces of its vertices, in
+  /// equal parts.
+  pub fn accumulate_forces(&self,
+                           points:    &[PointMass<N, V>],
+                           triangles: &[(uint, uint, uint)],
+                           t:         &N,
+                           forces:    &mut [V])
+  {
+    let third = One::one::<N>() / NumCast::from::<N, float>(3.0);
+    let half  = NumCast::from::<N, float>(0.5);
+
+    for &(a, b, c) in triangles.iter()
+    {
+      let (pa, pb, pc) = (&points[a], &points[b], &points[c]);
+
+      let mut normal = (pb.position - pa.position).cross(&(pc.position - pa.position));
+      let     area   = normal.normalize() * half;
+
+      if area.is_zero()
+      { loop }
+
+      let center   = (pa.position + pb.position + pc.position).scalar_mul(&third);
+      let velocity = (pa.velocity + pb.velocity + pc.velocity).scalar_mul(&third);
+      let mut vrel = velocity - self.wind.velocity(&center, t);
+      let speed    = vrel.normalize();
+
+      if speed.is_zero()
+      { loop }
+
+      // the normal facing the flow
+      let mut cos = normal.dot(&vrel);
+
+      if cos < Zero::zero()
+      {
+        normal = -normal;
+        cos    = -cos;
+      }
+
+      // dynamic pressure times the area exposed to the flow
+      let pressure = half * self.air_density * speed * speed * area * cos;
+
+      let mut force = vrel.scalar_mul(&(-pressure * self.drag_coefficient));
+
+      // lift: perpendicular to the flow, away from the exposed face
+      let mut lift = vrel.scalar_mul(&cos) - normal;
+      let     norm = lift.normalize();
+
+      if !norm.is_zero()
+      { force = force + lift.scalar_mul(&(pressure * self.lift_coefficient * cos)) }
+
+      let share = force.scalar_mul(&third);
+
+      forces[a] = forces[a] + share;
+      forces[b] = forces[b] + share;
+      forces[c] = forces[c] + share;
+    }
+  }
+}
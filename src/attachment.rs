@@ -0,0 +1,366 @@
+use std::vec;
+use std::num::{Zero, One};
+use nalgebra::traits::division_ring::DivisionRing;
+use nalgebra::traits::norm::Norm;
+use nalgebra::traits::dot::Dot;
+use nalgebra::traits::cross::Cross;
+use nalgebra::traits::vector_space::VectorSpace;
+use soft_body::{SoftBody, PointMass};
+
+/// A point held at a location of the world by a spring of compliance `compliance` (zero for a
+/// rigid attachment). Unlike a pin, the point keeps its mass and reacts to the other
+/// constraints.
+///
+/// If a target is given, the anchor moves along the trajectory `target(t)`.
+pub struct Anchor<N, V>
+{
+  point:      uint,
+  position:   V,
+  target:     Option<@fn(N) -> V>,
+  compliance: N
+}
+
+/// Location of a body a point of another body is attached to.
+pub enum Attachment<N>
+{
+  /// A point of the other body.
+  PointToPoint(uint),
+  /// The point of a triangle of the other body with the given barycentric coordinates.
+  PointToTriangle((uint, uint, uint), (N, N, N))
+}
+
+pub struct Stitch<N>
+{
+  point:      uint,
+  target:     Attachment<N>,
+  compliance: N
+}
+
+/// Attachments of the points of a body to locations of another body, typically to sew two
+/// panels together.
+///
+/// The two bodies are simulated separately: `project` must be called after each step of both
+/// bodies, with the same timestep. Stitches added to a `World` are instead resolved with the
+/// other constraints of its bodies, by `solve_velocities`.
+pub struct Stitches<N>
+{
+  stitches: ~[Stitch<N>]
+}
+
+impl<N: DivisionRing + NumCast + Signed + Orderable + Bounded + Round + Trigonometric + Eq + Ord + Clone,
+     V: VectorSpace<N> + Norm<N> + Dot<N> + Cross<V> + Clone>
+    SoftBody<N, V>
+{
+  /// Attaches the point `i` to its current position. Returns the index of the anchor.
+  pub fn add_anchor(&mut self, i: uint, compliance: N) -> uint
+  {
+    let position = self.points[i].position.clone();
+
+    self.push_anchor(i, position, None, compliance)
+  }
+
+  /// Attaches the point `i` to an anchor following `target(t)`. Returns the index of the anchor.
+  pub fn add_moving_anchor(&mut self, i: uint, target: @fn(N) -> V, compliance: N) -> uint
+  {
+    let position = target(self.time.clone());
+
+    self.push_anchor(i, position, Some(target), compliance)
+  }
+
+  pub fn remove_anchor(&mut self, i: uint) -> Anchor<N, V>
+  { self.anchors.remove(i) }
+
+  fn push_anchor(&mut self, i: uint, position: V, target: Option<@fn(N) -> V>, compliance: N) -> uint
+  {
+    self.anchors.push(Anchor {
+      point:      i,
+      position:   position,
+      target:     target,
+      compliance: compliance
+    });
+
+    self.anchors.len() - 1
+  }
+
+  /// Moves the anchored points toward their anchors, and updates their velocities accordingly.
+  ///
+  /// The anchors are projected `iterations` times, with a multiplier accumulated per anchor so
+  /// that their stiffness depends on their compliance only.
+  pub fn project_anchors(&mut self, dt: &N)
+  {
+    if self.anchors.is_empty()
+    { return }
+
+    let alpha_scale = One::one::<N>() / (*dt * *dt);
+    let mut lambdas = vec::from_elem(self.anchors.len(), Zero::zero::<V>());
+
+    for a in self.anchors.mut_iter()
+    {
+      match a.target
+      {
+        Some(target) => a.position = target(self.time.clone()),
+        None         => { }
+      }
+    }
+
+    for _ in range(0u, self.iterations)
+    {
+      for (a, lambda) in self.anchors.iter().zip(lambdas.mut_iter())
+      {
+        let p = &mut self.points[a.point];
+
+        if p.invmass.is_zero()
+        { loop }
+
+        let alpha = a.compliance * alpha_scale;
+        let delta = (a.position - p.position - lambda.scalar_mul(&alpha)).scalar_div(&(p.invmass + alpha));
+        let dx    = delta.scalar_mul(&p.invmass);
+
+        *lambda    = *lambda + delta;
+        p.position = p.position + dx;
+        p.velocity = p.velocity + dx.scalar_div(dt);
+      }
+    }
+  }
+}
+
+impl<N: DivisionRing + Ord + Clone> Stitches<N>
+{
+  pub fn new() -> Stitches<N>
+  { Stitches { stitches: ~[] } }
+
+  /// Attaches the point `i1` of the first body to the point `i2` of the second body.
+  pub fn add_point(&mut self, i1: uint, i2: uint, compliance: N)
+  {
+    self.stitches.push(Stitch {
+      point:      i1,
+      target:     PointToPoint(i2),
+      compliance: compliance
+    })
+  }
+
+  /// Attaches the point `i` of the first body to the point of the triangle `triangle` of the
+  /// second body with the barycentric coordinates `coords`.
+  pub fn add_barycentric(&mut self, i: uint, triangle: (uint, uint, uint), coords: (N, N, N), compliance: N)
+  {
+    self.stitches.push(Stitch {
+      point:      i,
+      target:     PointToTriangle(triangle, coords),
+      compliance: compliance
+    })
+  }
+
+  /// Attaches the point `i` of `body1` to the point of the triangle `triangle` of `body2` closest
+  /// to its current position.
+  pub fn add_closest_on_triangle<V: VectorSpace<N> + Norm<N> + Dot<N> + Cross<V> + Clone>(
+                                 &mut self,
+                                 body1:      &SoftBody<N, V>,
+                                 i:          uint,
+                                 body2:      &SoftBody<N, V>,
+                                 triangle:   (uint, uint, uint),
+                                 compliance: N)
+  {
+    let (a, b, c) = triangle;
+    let coords    = barycentric_coordinates(&body1.points[i].position,
+                                            &body2.points[a].position,
+                                            &body2.points[b].position,
+                                            &body2.points[c].position);
+
+    self.add_barycentric(i, triangle, coords, compliance)
+  }
+
+  pub fn len(&self) -> uint
+  { self.stitches.len() }
+
+  /// Moves the stitched points of both bodies toward each other, `iterations` times, and updates
+  /// their velocities accordingly. `dt` is the timestep the bodies were last stepped with.
+  ///
+  /// A multiplier is accumulated per stitch during the call, so that the stiffness of the
+  /// stitches depends on their compliance only.
+  pub fn project<V: VectorSpace<N> + Norm<N> + Dot<N> + Cross<V> + Clone>(
+                 &self,
+                 body1:      &mut SoftBody<N, V>,
+                 body2:      &mut SoftBody<N, V>,
+                 dt:         &N,
+                 iterations: uint)
+  {
+    let alpha_scale = One::one::<N>() / (*dt * *dt);
+    let mut lambdas = vec::from_elem(self.stitches.len(), Zero::zero::<V>());
+
+    for _ in range(0u, iterations)
+    {
+      for (s, lambda) in self.stitches.iter().zip(lambdas.mut_iter())
+      {
+        let alpha = s.compliance * alpha_scale;
+
+        match s.target
+        {
+          PointToPoint(j) =>
+            project_stitch(&mut body1.points[s.point], body2.points, &[j], &[One::one()], &alpha,
+                           lambda, dt),
+          PointToTriangle((a, b, c), (ref wa, ref wb, ref wc)) =>
+            project_stitch(&mut body1.points[s.point], body2.points, &[a, b, c],
+                           &[wa.clone(), wb.clone(), wc.clone()], &alpha, lambda, dt)
+        }
+      }
+    }
+  }
+
+  /// Velocity-level resolution of the stitches between bodies solved together: the points of the
+  /// first body are numbered from `offset1` in `points`, those of the second body from `offset2`.
+  /// `lambdas` are the multipliers of the stitches, accumulated during the current solve. The
+  /// stitches are as soft as with `project`.
+  pub fn solve_velocities<V: VectorSpace<N> + Clone>(&self,
+                                                     points:  &mut [PointMass<N, V>],
+                                                     offset1: uint,
+                                                     offset2: uint,
+                                                     dt:      &N,
+                                                     fext_dt: &V,
+                                                     lambdas: &mut [V])
+  {
+    let alpha_scale = One::one::<N>() / (*dt * *dt);
+
+    for (s, lambda) in self.stitches.iter().zip(lambdas.mut_iter())
+    {
+      let alpha = s.compliance * alpha_scale;
+      let p     = offset1 + s.point;
+
+      match s.target
+      {
+        PointToPoint(j) =>
+          solve_stitch_velocity(points, p, &[offset2 + j], &[One::one()], &alpha, lambda, dt, fext_dt),
+        PointToTriangle((a, b, c), (ref wa, ref wb, ref wc)) =>
+          solve_stitch_velocity(points, p, &[offset2 + a, offset2 + b, offset2 + c],
+                                &[wa.clone(), wb.clone(), wc.clone()], &alpha, lambda, dt, fext_dt)
+      }
+    }
+  }
+}
+
+// Projects the constraint `p = sum(weights[k] * points[ids[k]])`, with the multiplier `lambda`
+// accumulated during the current projection.
+fn project_stitch<N: DivisionRing + Clone, V: VectorSpace<N> + Clone>(
+                  p:       &mut PointMass<N, V>,
+                  points:  &mut [PointMass<N, V>],
+                  ids:     &[uint],
+                  weights: &[N],
+                  alpha:   &N,
+                  lambda:  &mut V,
+                  dt:      &N)
+{
+  let mut target = Zero::zero::<V>();
+  let mut denom  = p.invmass.clone();
+
+  for (i, w) in ids.iter().zip(weights.iter())
+  {
+    target = target + points[*i].position.scalar_mul(w);
+    denom  = denom + *w * *w * points[*i].invmass;
+  }
+
+  if denom.is_zero()
+  { return }
+
+  let delta = (target - p.position - lambda.scalar_mul(alpha)).scalar_div(&(denom + *alpha));
+  let dx    = delta.scalar_mul(&p.invmass);
+
+  *lambda = *lambda + delta;
+
+  p.position = p.position + dx;
+  p.velocity = p.velocity + dx.scalar_div(dt);
+
+  for (i, w) in ids.iter().zip(weights.iter())
+  {
+    let q  = &mut points[*i];
+    let dx = delta.scalar_mul(&(*w * q.invmass));
+
+    q.position = q.position - dx;
+    q.velocity = q.velocity - dx.scalar_div(dt);
+  }
+}
+
+// Velocity-level counterpart of `project_stitch`: changes the velocities so that the constraint
+// `points[p] = sum(weights[k] * points[ids[k]])` is projected on the positions the points will
+// have after the next integration.
+fn solve_stitch_velocity<N: DivisionRing + Clone, V: VectorSpace<N> + Clone>(
+                         points:  &mut [PointMass<N, V>],
+                         p:       uint,
+                         ids:     &[uint],
+                         weights: &[N],
+                         alpha:   &N,
+                         lambda:  &mut V,
+                         dt:      &N,
+                         fext_dt: &V)
+{
+  // velocity of a point at the next integration
+  let next = |q: &PointMass<N, V>| -> V {
+    if q.invmass.is_zero() { q.velocity.clone() } else { q.velocity + *fext_dt }
+  };
+
+  let mut target = Zero::zero::<V>();
+  let mut vel    = Zero::zero::<V>();
+  let mut denom  = points[p].invmass.clone();
+
+  for (i, w) in ids.iter().zip(weights.iter())
+  {
+    target = target + points[*i].position.scalar_mul(w);
+    vel    = vel + next(&points[*i]).scalar_mul(w);
+    denom  = denom + *w * *w * points[*i].invmass;
+  }
+
+  if denom.is_zero()
+  { return }
+
+  let error = (target - points[p].position).scalar_div(dt) + vel - next(&points[p]);
+  let delta = (error - lambda.scalar_mul(alpha)).scalar_div(&(denom + *alpha));
+
+  *lambda = *lambda + delta;
+
+  points[p].velocity = points[p].velocity + delta.scalar_mul(&points[p].invmass);
+
+  for (i, w) in ids.iter().zip(weights.iter())
+  {
+    let q = &mut points[*i];
+
+    q.velocity = q.velocity - delta.scalar_mul(&(*w * q.invmass));
+  }
+}
+
+/// Barycentric coordinates of the projection of `p` on the triangle `(a, b, c)`, clamped to the
+/// triangle.
+pub fn barycentric_coordinates<N: DivisionRing + Ord + Clone, V: VectorSpace<N> + Dot<N> + Clone>(
+                               p: &V,
+                               a: &V,
+                               b: &V,
+                               c: &V) -> (N, N, N)
+{
+  let ab  = *b - *a;
+  let ac  = *c - *a;
+  let ap  = *p - *a;
+  let d00 = ab.dot(&ab);
+  let d01 = ab.dot(&ac);
+  let d11 = ac.dot(&ac);
+  let d20 = ap.dot(&ab);
+  let d21 = ap.dot(&ac);
+  let det = d00 * d11 - d01 * d01;
+
+  if det.is_zero()
+  { return (One::one(), Zero::zero(), Zero::zero()) }
+
+  let mut v = (d11 * d20 - d01 * d21) / det;
+  let mut w = (d00 * d21 - d01 * d20) / det;
+
+  // clamping each coordinate and renormalizing is not the exact closest point, but is close
+  // enough for points near the triangle
+  if v < Zero::zero() { v = Zero::zero() }
+  if w < Zero::zero() { w = Zero::zero() }
+
+  let sum = v + w;
+
+  if sum > One::one()
+  {
+    v = v / sum;
+    w = w / sum;
+  }
+
+  (One::one::<N>() - v - w, v, w)
+}
@@ -0,0 +1,82 @@
+use std::num::{Zero, One};
+use nalgebra::traits::division_ring::DivisionRing;
+use nalgebra::traits::norm::Norm;
+use nalgebra::traits::dot::Dot;
+use nalgebra::traits::cross::Cross;
+use nalgebra::traits::vector_space::VectorSpace;
+
+/// Constraint on the dihedral angle between two adjacent triangles.
+///
+/// `ids` are the two vertices of the shared edge followed by the vertex opposite to the edge in
+/// each triangle, as given by `Mesh::bending_pairs`.
+pub struct BendingConstraint<N>
+{
+  ids:        (uint, uint, uint, uint),
+  stiffness:  N,
+  compliance: N,
+  rest_angle: N,
+  impulse:    N
+}
+
+impl<N: DivisionRing + Trigonometric + Clone> BendingConstraint<N>
+{
+  /// Creates a constraint whose rest angle is the current angle of the triangles.
+  pub fn new<V: VectorSpace<N> + Norm<N> + Dot<N> + Cross<V> + Clone>(
+             ids:       (uint, uint, uint, uint),
+             positions: &[V],
+             stiffness: N) -> BendingConstraint<N>
+  {
+    let (e0, e1, o1, o2) = ids;
+    let rest_angle = match dihedral_angle(&positions[e0], &positions[e1], &positions[o1], &positions[o2])
+    {
+      Some((angle, _)) => angle,
+      None             => Zero::zero()
+    };
+
+    BendingConstraint {
+      ids:        ids,
+      compliance: if stiffness.is_zero() { Zero::zero() } else { One::one::<N>() / stiffness },
+      stiffness:  stiffness,
+      rest_angle: rest_angle,
+      impulse:    Zero::zero()
+    }
+  }
+
+  pub fn id_list(&self) -> ~[uint]
+  {
+    let (e0, e1, o1, o2) = self.ids;
+
+    ~[e0, e1, o1, o2]
+  }
+}
+
+/// Signed angle between the triangles `(e0, e1, o1)` and `(e0, e1, o2)`, with its gradient with
+/// respect to `e0`, `e1`, `o1` and `o2`. Returns `None` for degenerate triangles.
+pub fn dihedral_angle<N: DivisionRing + Trigonometric + Clone,
+                      V: VectorSpace<N> + Norm<N> + Dot<N> + Cross<V> + Clone>(
+                      e0: &V, e1: &V, o1: &V, o2: &V) -> Option<(N, ~[V])>
+{
+  let e   = *e1 - *e0;
+  let len = e.norm();
+  let n1  = (*o1 - *e0).cross(&(*o1 - *e1));
+  let n2  = (*o2 - *e1).cross(&(*o2 - *e0));
+  let sq1 = n1.sqnorm();
+  let sq2 = n2.sqnorm();
+
+  if len.is_zero() || sq1.is_zero() || sq2.is_zero()
+  { return None }
+
+  let un1   = n1.normalized();
+  let un2   = n2.normalized();
+  let angle = un2.cross(&un1).dot(&e.scalar_div(&len)).atan2(&un1.dot(&un2));
+
+  let a = n1.scalar_div(&sq1);
+  let b = n2.scalar_div(&sq2);
+
+  let g_o1 = a.scalar_mul(&len);
+  let g_o2 = b.scalar_mul(&len);
+  let g_e0 = a.scalar_mul(&((*o1 - *e1).dot(&e) / len)) + b.scalar_mul(&((*o2 - *e1).dot(&e) / len));
+  let g_e1 = -(a.scalar_mul(&((*o1 - *e0).dot(&e) / len)) + b.scalar_mul(&((*o2 - *e0).dot(&e) / len)));
+
+  Some((angle, ~[g_e0, g_e1, g_o1, g_o2]))
+}
@@ -1,7 +1,7 @@
-use std::vec;
 use nalgebra::vec::Vec3;
-use kiss3d::object::{VerticesNormalsTriangles, Object};
 use graph::{Mesh, Graph};
+use tet_mesh::TetMesh;
+use material::MaterialMap;
 
 pub fn cg2ids(graph: &mut Graph) -> (~[Vec3<f64>],
                                  ~[i32],
@@ -88,44 +88,101 @@ pub fn cg2ids_no_blob(graph: &mut Graph) -> (~[Vec3<f64>],
   (vertices, ids1, ids2, colors, colors_sizes, batches, batch_sizes)
 }
 
-pub fn soft_body_parameters(quad: @mut Object, w: uint, color_graph: bool) -> (~[Vec3<f64>], ~[i32], ~[i32], ~[i32], ~[i32], ~[i32], ~[i32], ~[f64], ~[f64])
+pub fn mesh_parameters(mesh: Mesh, spring_bending: bool, color_graph: bool) -> (~[Vec3<f64>], ~[i32], ~[i32], ~[i32], ~[i32], ~[i32], ~[i32])
 {
-  match quad.geometry()
+  let mut graph = Graph::new(mesh);
+
+  // distance constraints between vertices at distance 2, to approximate bending
+  if spring_bending
+  { graph.augment() }
+
+  graph.build_edge_graph();
+
+  if color_graph
   {
-    &VerticesNormalsTriangles(ref vs, _, ref ts) =>
-    {
-      let     mesh  = Mesh::new(vs.clone(), ts.clone());
-      let mut graph = Graph::new(mesh);
+    println("Preprocessing, please wait...");
+    //graph.build_blob_graph(0, 0);
+    graph.color_edge_graph();
+    cg2ids_no_blob(&mut graph)
+  }
 
-      graph.augment();
-      graph.build_edge_graph();
+  else
+  {
+    let (mvs, ids1_cpu, ids2_cpu) = graph.export();
+    (mvs, ids1_cpu, ids2_cpu, ~[], ~[], ~[], ~[])
+  }
+}
 
+pub fn soft_body_parameters(mesh: Mesh, materials: &MaterialMap, spring_bending: bool, color_graph: bool) -> (~[Vec3<f64>], ~[i32], ~[i32], ~[i32], ~[i32], ~[i32], ~[i32], ~[f64], ~[f64])
+{
+  let invmasses = materials.invmasses(&mesh);
+  let rest_mesh = mesh.clone();
 
-      let (vertices, ids1, ids2, colors, colors_sizes, batches, batch_sizes) =
-      if color_graph
-      {
-        println("Preprocessing, please wait...");
-        //graph.build_blob_graph(0, 0);
-        graph.color_edge_graph();
-        cg2ids_no_blob(&mut graph)
-      }
+  let (vertices, ids1, ids2, colors, colors_sizes, batches, batch_sizes) =
+    mesh_parameters(mesh, spring_bending, color_graph);
 
-      else
-      {
-        let (mvs, ids1_cpu, ids2_cpu) = graph.export();
-        (mvs, ids1_cpu, ids2_cpu, ~[], ~[], ~[], ~[])
-      };
+  // vertices are pinned on the soft body itself.
+  let stiffness = materials.stiffness(&rest_mesh, ids1, ids2);
+
+  (vertices, ids1, ids2, colors, colors_sizes, batches, batch_sizes, invmasses, stiffness)
+}
 
-      let mut invmasses = vec::from_elem(vertices.len(), 1.0f64);
-      // invmasses[0] = 0.0;
-      // invmasses[w] = 0.0;
-      invmasses[vertices.len() - 1]     = 0.0;
-      invmasses[vertices.len() - w - 1] = 0.0;
+/// Dihedral bending constraints of the mesh: the vertices of each pair of adjacent triangles (see
+/// `Mesh::bending_pairs`) and their stiffness.
+pub fn dihedral_parameters(mesh: &Mesh, materials: &MaterialMap) -> (~[(uint, uint, uint, uint)], ~[f64])
+{
+  let pairs     = mesh.bending_pairs();
+  let stiffness = materials.bending_stiffness(pairs);
 
-      let stiffness = vec::from_elem(ids1.len(), 50.0f64);
+  (pairs, stiffness)
+}
 
-      (vertices, ids1, ids2, colors, colors_sizes, batches, batch_sizes, invmasses, stiffness)
-    },
-    _ => fail!("Unable to build the soft body without geometric informations.")
-  }
+/// Membrane elements of the mesh: the vertices of each triangle of `Mesh::ibuff`, and their
+/// Young's moduli along the warp and the weft directions and Poisson ratio.
+pub fn membrane_parameters(mesh: &Mesh, materials: &MaterialMap) -> (~[(uint, uint, uint)], ~[(f64, f64, f64)])
+{
+  let triangles: ~[(uint, uint, uint)] = mesh.ibuff.iter().transform(|&(a, b, c)| {
+    (a as uint, b as uint, c as uint)
+  }).collect();
+  let params    = materials.membrane_parameters(triangles);
+
+  (triangles, params)
+}
+
+/// Parameters of a solid: its vertices, a distance constraint along each edge of its tetrahedra,
+/// the inverse masses of the vertices and the stiffness of the constraints.
+pub fn tet_soft_body_parameters(tets: &TetMesh, materials: &MaterialMap) -> (~[Vec3<f64>], ~[i32], ~[i32], ~[f64], ~[f64])
+{
+  let vertices = tets.vbuff.iter().transform(|v| Vec3::new(v.x as f64, v.y as f64, v.z as f64)).collect();
+  let edges    = tets.edges();
+  let ids1: ~[i32] = edges.iter().transform(|&(a, _)| a as i32).collect();
+  let ids2: ~[i32] = edges.iter().transform(|&(_, b)| b as i32).collect();
+
+  let invmasses = materials.tet_invmasses(tets);
+  let stiffness = materials.tet_stiffness(ids1, ids2);
+
+  (vertices, ids1, ids2, invmasses, stiffness)
+}
+
+/// Volume constraints of a solid: the vertices of each tetrahedron and their stiffness.
+pub fn volume_parameters(tets: &TetMesh, materials: &MaterialMap) -> (~[(uint, uint, uint, uint)], ~[f64])
+{
+  let tetrahedra: ~[(uint, uint, uint, uint)] = tets.tbuff.iter().transform(|&(a, b, c, d)| {
+    (a as uint, b as uint, c as uint, d as uint)
+  }).collect();
+  let stiffness  = materials.volume_stiffness(tetrahedra);
+
+  (tetrahedra, stiffness)
+}
+
+/// Groups of vertices for shape matching: the vertices of each blob of edges within `dist` of a
+/// seed edge, as formed by `Graph::build_blob_graph`.
+pub fn blob_groups(mesh: Mesh, dist: uint) -> ~[~[uint]]
+{
+  let mut graph = Graph::new(mesh);
+
+  graph.build_edge_graph();
+  graph.build_blob_graph(dist, 0);
+
+  graph.export_blob_vertices()
 }
@@ -0,0 +1,157 @@
+use std::num::{Zero, One};
+use nalgebra::traits::division_ring::DivisionRing;
+use nalgebra::traits::norm::Norm;
+use nalgebra::traits::dot::Dot;
+use nalgebra::traits::vector_space::VectorSpace;
+
+/// Analytic shapes, expressed relative to the center of their collider.
+pub enum Shape<N, V>
+{
+  /// Half-space below the plane of the given unit normal.
+  Plane(V),
+  /// Sphere of the given radius.
+  Sphere(N),
+  /// Capsule around the segment `[-half_segment, half_segment]`, with the given radius.
+  Capsule(V, N),
+  /// Box with the given unit axes and half extents along each axis.
+  OrientedBox(~[V], ~[N])
+}
+
+/// A static or kinematic obstacle for the soft bodies.
+pub struct Collider<N, V>
+{
+  shape:    Shape<N, V>,
+  center:   V,
+  velocity: V,
+  friction: N,
+  /// Trajectory of the center, as a function of time. Without it, the collider moves at a constant
+  /// `velocity`.
+  motion:   Option<@fn(N) -> V>
+}
+
+impl<N: DivisionRing + NumCast + Signed + Orderable + Bounded + Clone,
+     V: VectorSpace<N> + Norm<N> + Dot<N> + Clone>
+    Collider<N, V>
+{
+  pub fn new(shape: Shape<N, V>, center: V, friction: N) -> Collider<N, V>
+  {
+    Collider {
+      shape:    shape,
+      center:   center,
+      velocity: Zero::zero(),
+      friction: friction,
+      motion:   None
+    }
+  }
+
+  pub fn new_kinematic(shape: Shape<N, V>, motion: @fn(N) -> V, t: N, friction: N) -> Collider<N, V>
+  {
+    let mut res = Collider::new(shape, motion(t), friction);
+
+    res.motion = Some(motion);
+
+    res
+  }
+
+  /// Moves the collider to its position at time `t`, `dt` being the time elapsed since the last
+  /// update.
+  pub fn update(&mut self, t: N, dt: &N)
+  {
+    match self.motion
+    {
+      Some(motion) =>
+      {
+        let center = motion(t);
+
+        self.velocity = (center - self.center).scalar_div(dt);
+        self.center   = center;
+      },
+      None => self.center = self.center + self.velocity.scalar_mul(dt)
+    }
+  }
+
+  /// Returns the outward unit normal and the signed distance from `point` to the collider, if
+  /// this distance is smaller than `margin`.
+  pub fn contact(&self, point: &V, margin: &N) -> Option<(V, N)>
+  {
+    let local = *point - self.center;
+
+    let (normal, dist) = match self.shape
+    {
+      Plane(ref n) => (n.clone(), local.dot(n)),
+      Sphere(ref radius) =>
+      {
+        match sphere_contact(&local, radius)
+        {
+          Some(c) => c,
+          None    => return None
+        }
+      },
+      Capsule(ref half_segment, ref radius) =>
+      {
+        let sqlen = half_segment.sqnorm();
+        let mut t = if sqlen.is_zero() { Zero::zero() } else { local.dot(half_segment) / sqlen };
+
+        t = t.max(&-One::one::<N>()).min(&One::one());
+
+        match sphere_contact(&(local - half_segment.scalar_mul(&t)), radius)
+        {
+          Some(c) => c,
+          None    => return None
+        }
+      },
+      OrientedBox(ref axes, ref half_extents) =>
+      {
+        let mut inside    = true;
+        let mut closest   = Zero::zero::<V>();
+        let mut min_depth = Bounded::max_value::<N>();
+        let mut min_axis  = Zero::zero::<V>();
+
+        for (axis, extent) in axes.iter().zip(half_extents.iter())
+        {
+          let l = local.dot(axis);
+
+          if l.abs() > *extent
+          { inside = false }
+
+          closest = closest + axis.scalar_mul(&l.max(&-*extent).min(extent));
+
+          let depth = *extent - l.abs();
+
+          if depth < min_depth
+          {
+            min_depth = depth;
+            min_axis  = if l < Zero::zero() { -*axis } else { axis.clone() };
+          }
+        }
+
+        if inside
+        { (min_axis, -min_depth) }
+        else
+        {
+          let mut n = local - closest;
+          let     d = n.normalize();
+
+          (n, d)
+        }
+      }
+    };
+
+    if dist < *margin
+    { Some((normal, dist)) }
+    else
+    { None }
+  }
+}
+
+fn sphere_contact<N: DivisionRing + Clone, V: VectorSpace<N> + Norm<N> + Clone>(local: &V, radius: &N) -> Option<(V, N)>
+{
+  let mut n   = local.clone();
+  let     len = n.normalize();
+
+  // the normal is undefined at the center
+  if len.is_zero()
+  { None }
+  else
+  { Some((n, len - *radius)) }
+}
@@ -0,0 +1,59 @@
+use std::num::{Zero, One};
+use nalgebra::traits::division_ring::DivisionRing;
+use nalgebra::traits::norm::Norm;
+use nalgebra::traits::dot::Dot;
+use nalgebra::traits::cross::Cross;
+use nalgebra::traits::vector_space::VectorSpace;
+use soft_body::SoftBody;
+
+impl<N: DivisionRing + NumCast + Signed + Orderable + Bounded + Round + Trigonometric + Eq + Ord + Clone,
+     V: VectorSpace<N> + Norm<N> + Dot<N> + Cross<V> + Clone>
+    SoftBody<N, V>
+{
+  /// Sets the damping coefficient of each constraint and the velocity decay rate of each point,
+  /// as given by `MaterialMap::constraint_damping` and `MaterialMap::point_damping`.
+  pub fn set_damping(&mut self, constraint_damping: &[N], point_damping: &[N])
+  {
+    assert!(constraint_damping.len() == self.constraints.len(),
+            "Constraints and damping informations must have the same size.");
+    assert!(point_damping.len() == self.points.len(),
+            "Points and damping informations must have the same size.");
+
+    for (c, d) in self.constraints.mut_iter().zip(constraint_damping.iter())
+    { c.damping = d.clone() }
+
+    for (p, d) in self.points.mut_iter().zip(point_damping.iter())
+    { p.damping = d.clone() }
+  }
+
+  /// Reduces the relative velocity of the points of each constraint along its direction, as a
+  /// damper of coefficient `damping` would during `dt`. The result does not depend on the solver.
+  pub fn damp_constraints(&mut self, dt: &N)
+  {
+    for c in self.constraints.iter()
+    {
+      if c.damping.is_zero()
+      { loop }
+
+      let w1 = self.points[c.rb1].invmass.clone();
+      let w2 = self.points[c.rb2].invmass.clone();
+
+      if (w1 + w2).is_zero()
+      { loop }
+
+      let mut normal = self.points[c.rb1].position - self.points[c.rb2].position;
+      let     length = normal.normalize();
+
+      if length.is_zero()
+      { loop }
+
+      // the damper cannot revert the relative velocity
+      let vn      = (self.points[c.rb1].velocity - self.points[c.rb2].velocity).dot(&normal);
+      let ratio   = (c.damping * (w1 + w2) * *dt).min(&One::one());
+      let impulse = vn * ratio / (w1 + w2);
+
+      self.points[c.rb1].velocity = self.points[c.rb1].velocity - normal.scalar_mul(&(w1 * impulse));
+      self.points[c.rb2].velocity = self.points[c.rb2].velocity + normal.scalar_mul(&(w2 * impulse));
+    }
+  }
+}
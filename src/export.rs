@@ -0,0 +1,219 @@
+use std::io;
+use nalgebra::vec::Vec3;
+use nalgebra::traits::norm::Norm;
+
+static CACHE_MAGIC:   &'static str = "ROFTCACH";
+static CACHE_VERSION: u32          = 1;
+// magic + version + vertex count + frame count + timestep
+static CACHE_HEADER_SIZE: uint     = 8 + 4 + 4 + 4 + 8;
+
+/// Writes one frame as a Wavefront OBJ file.
+pub fn write_obj(path: &Path, positions: &[Vec3<f64>], triangles: &[(u32, u32, u32)]) -> Result<(), ~str>
+{
+  let out = match io::file_writer(path, [io::Create, io::Truncate])
+  {
+    Ok(w)  => w,
+    Err(e) => return Err(e)
+  };
+
+  for p in positions.iter()
+  { out.write_line("v " + p.x.to_str() + " " + p.y.to_str() + " " + p.z.to_str()) }
+
+  // OBJ indices start at 1
+  for &(a, b, c) in triangles.iter()
+  { out.write_line("f " + (a + 1).to_str() + " " + (b + 1).to_str() + " " + (c + 1).to_str()) }
+
+  Ok(())
+}
+
+/// Writes successive frames as `<prefix>_0000.obj`, `<prefix>_0001.obj`, …
+pub struct ObjSequence
+{
+  prefix:          ~str,
+  triangles:       ~[(u32, u32, u32)],
+  priv next_frame: uint
+}
+
+impl ObjSequence
+{
+  pub fn new(prefix: ~str, triangles: ~[(u32, u32, u32)]) -> ObjSequence
+  {
+    ObjSequence {
+      prefix:     prefix,
+      triangles:  triangles,
+      next_frame: 0
+    }
+  }
+
+  pub fn frame_path(&self, frame: uint) -> Path
+  { Path(fmt!("%s_%04u.obj", self.prefix, frame)) }
+
+  pub fn write_frame(&mut self, positions: &[Vec3<f64>]) -> Result<(), ~str>
+  {
+    let path = self.frame_path(self.next_frame);
+
+    self.next_frame = self.next_frame + 1;
+
+    write_obj(&path, positions, self.triangles)
+  }
+}
+
+/// Streams frames to a binary cache file.
+///
+/// Layout (little endian): the `ROFTCACH` magic, the format version (u32), the vertex count (u32),
+/// the frame count (u32) and the timestep (f64), followed by the frames, each one being the `x y z`
+/// coordinates of every vertex as f32.
+pub struct CacheWriter
+{
+  priv out:       @io::Writer,
+  priv nvertices: uint,
+  priv nframes:   uint
+}
+
+impl CacheWriter
+{
+  pub fn create(path: &Path, nvertices: uint, timestep: f64) -> Result<CacheWriter, ~str>
+  {
+    let out = match io::file_writer(path, [io::Create, io::Truncate])
+    {
+      Ok(w)  => w,
+      Err(e) => return Err(e)
+    };
+
+    let res = CacheWriter {
+      out:       out,
+      nvertices: nvertices,
+      nframes:   0
+    };
+
+    res.write_header(timestep);
+
+    Ok(res)
+  }
+
+  fn write_header(&self, timestep: f64)
+  {
+    self.out.write_str(CACHE_MAGIC);
+    self.out.write_le_u32(CACHE_VERSION);
+    self.out.write_le_u32(self.nvertices as u32);
+    self.out.write_le_u32(self.nframes as u32);
+    self.out.write_le_f64(timestep);
+  }
+
+  pub fn write_frame(&mut self, positions: &[Vec3<f64>])
+  {
+    assert!(positions.len() == self.nvertices,
+            "The number of vertices cannot change during a cache recording.");
+
+    for p in positions.iter()
+    {
+      self.out.write_le_f32(p.x as f32);
+      self.out.write_le_f32(p.y as f32);
+      self.out.write_le_f32(p.z as f32);
+    }
+
+    self.nframes = self.nframes + 1;
+  }
+
+  /// Patches the frame count in the header. Must be called once all frames are written.
+  pub fn finish(&mut self)
+  {
+    self.out.seek(16, io::SeekSet);
+    self.out.write_le_u32(self.nframes as u32);
+    self.out.seek(0, io::SeekEnd);
+    self.out.flush();
+  }
+}
+
+/// A cache loaded in memory, for playback or comparison between two runs.
+pub struct Cache
+{
+  nvertices: uint,
+  timestep:  f64,
+  frames:    ~[~[Vec3<f32>]]
+}
+
+impl Cache
+{
+  pub fn load(path: &Path) -> Result<Cache, ~str>
+  {
+    let bytes = match io::read_whole_file(path)
+    {
+      Ok(b)  => b,
+      Err(e) => return Err(e)
+    };
+
+    if bytes.len() < CACHE_HEADER_SIZE || bytes.slice(0, 8) != CACHE_MAGIC.as_bytes()
+    { return Err(~"Not a roft cache file.") }
+
+    do io::with_bytes_reader(bytes) |r|
+    {
+      let _         = r.read_bytes(8);
+      let version   = r.read_le_u32();
+      let nvertices = r.read_le_u32() as uint;
+      let nframes   = r.read_le_u32() as uint;
+      let timestep  = r.read_le_f64();
+
+      if version != CACHE_VERSION
+      { Err("Unsupported cache version: " + version.to_str()) }
+      else if bytes.len() != CACHE_HEADER_SIZE + nframes * nvertices * 12
+      { Err(~"Truncated or corrupted cache file.") }
+      else
+      {
+        let mut frames = ~[];
+
+        for _ in range(0u, nframes)
+        {
+          let mut frame = ~[];
+
+          for _ in range(0u, nvertices)
+          {
+            let x = r.read_le_f32();
+            let y = r.read_le_f32();
+            let z = r.read_le_f32();
+
+            frame.push(Vec3::new(x, y, z));
+          }
+
+          frames.push(frame);
+        }
+
+        Ok(Cache {
+          nvertices: nvertices,
+          timestep:  timestep,
+          frames:    frames
+        })
+      }
+    }
+  }
+
+  pub fn nframes(&self) -> uint
+  { self.frames.len() }
+
+  pub fn frame<'r>(&'r self, i: uint) -> &'r [Vec3<f32>]
+  { self.frames[i].as_slice() }
+
+  /// Maximum vertex displacement between the two caches, for each frame they have in common.
+  pub fn max_distances(&self, other: &Cache) -> Result<~[f32], ~str>
+  {
+    if self.nvertices != other.nvertices
+    {
+      return Err("Caches have different vertex counts: " + self.nvertices.to_str() +
+                 " and " + other.nvertices.to_str() + ".")
+    }
+
+    let mut res = ~[];
+
+    for (f1, f2) in self.frames.iter().zip(other.frames.iter())
+    {
+      let mut max = 0.0f32;
+
+      for (p1, p2) in f1.iter().zip(f2.iter())
+      { max = max.max(&(*p1 - *p2).norm()) }
+
+      res.push(max);
+    }
+
+    Ok(res)
+  }
+}
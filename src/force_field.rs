@@ -0,0 +1,167 @@
+use std::num::{Zero, One};
+use std::hashmap::HashMap;
+use nalgebra::traits::division_ring::DivisionRing;
+use nalgebra::traits::norm::Norm;
+use nalgebra::traits::dot::Dot;
+use nalgebra::traits::cross::Cross;
+use nalgebra::traits::vector_space::VectorSpace;
+
+/// External force evaluated on each point at each step.
+pub trait ForceField<N, V>
+{
+  /// Force applied at the time `t` on the point `i`. Only called for points which are not
+  /// pinned: `invmass` is never zero.
+  fn force(&self, i: uint, position: &V, velocity: &V, invmass: &N, t: &N) -> V;
+}
+
+/// Uniform acceleration. The uniform acceleration given to `SoftBody::integrate` is the same,
+/// without the indirection.
+pub struct Gravity<V>
+{
+  acceleration: V
+}
+
+impl<V> Gravity<V>
+{
+  pub fn new(acceleration: V) -> Gravity<V>
+  { Gravity { acceleration: acceleration } }
+}
+
+impl<N: DivisionRing, V: VectorSpace<N> + Clone> ForceField<N, V> for Gravity<V>
+{
+  fn force(&self, _: uint, _: &V, _: &V, invmass: &N, _: &N) -> V
+  { self.acceleration.scalar_div(invmass) }
+}
+
+/// Acceleration toward `center`, of magnitude `strength / (1 + distance^2 / radius^2)`.
+/// Negative strengths repel.
+pub struct PointAttractor<N, V>
+{
+  center:   V,
+  strength: N,
+  radius:   N
+}
+
+impl<N, V> PointAttractor<N, V>
+{
+  pub fn new(center: V, strength: N, radius: N) -> PointAttractor<N, V>
+  {
+    PointAttractor {
+      center:   center,
+      strength: strength,
+      radius:   radius
+    }
+  }
+}
+
+impl<N: DivisionRing + Clone, V: VectorSpace<N> + Norm<N> + Clone> ForceField<N, V> for PointAttractor<N, V>
+{
+  fn force(&self, _: uint, position: &V, _: &V, invmass: &N, _: &N) -> V
+  {
+    let mut dir  = self.center - *position;
+    let     dist = dir.normalize();
+
+    if dist.is_zero()
+    { return Zero::zero() }
+
+    let ratio = dist / self.radius;
+
+    dir.scalar_mul(&(self.strength / ((One::one::<N>() + ratio * ratio) * *invmass)))
+  }
+}
+
+/// Acceleration around the line through `center` along the unit vector `axis`, of magnitude
+/// `strength / (1 + distance / radius)`.
+pub struct Vortex<N, V>
+{
+  center:   V,
+  axis:     V,
+  strength: N,
+  radius:   N
+}
+
+impl<N, V> Vortex<N, V>
+{
+  pub fn new(center: V, axis: V, strength: N, radius: N) -> Vortex<N, V>
+  {
+    Vortex {
+      center:   center,
+      axis:     axis,
+      strength: strength,
+      radius:   radius
+    }
+  }
+}
+
+impl<N: DivisionRing + Clone, V: VectorSpace<N> + Norm<N> + Dot<N> + Cross<V> + Clone>
+    ForceField<N, V> for Vortex<N, V>
+{
+  fn force(&self, _: uint, position: &V, _: &V, invmass: &N, _: &N) -> V
+  {
+    let r    = *position - self.center;
+    let perp = r - self.axis.scalar_mul(&r.dot(&self.axis));
+    let dist = perp.norm();
+
+    if dist.is_zero()
+    { return Zero::zero() }
+
+    let tangent = self.axis.cross(&perp).scalar_div(&dist);
+
+    tangent.scalar_mul(&(self.strength / ((One::one::<N>() + dist / self.radius) * *invmass)))
+  }
+}
+
+/// Springs pulling some points toward target positions.
+pub struct SpringToTarget<N, V>
+{
+  targets:   HashMap<uint, V>,
+  stiffness: N
+}
+
+impl<N, V> SpringToTarget<N, V>
+{
+  pub fn new(ids: &[uint], targets: ~[V], stiffness: N) -> SpringToTarget<N, V>
+  {
+    assert!(ids.len() == targets.len(), "Points and targets must have the same size.");
+
+    let mut map = HashMap::new();
+
+    for (id, target) in ids.iter().zip(targets.consume_iter())
+    { map.insert(*id, target); }
+
+    SpringToTarget {
+      targets:   map,
+      stiffness: stiffness
+    }
+  }
+}
+
+impl<N: DivisionRing + Clone, V: VectorSpace<N> + Clone> ForceField<N, V> for SpringToTarget<N, V>
+{
+  fn force(&self, i: uint, position: &V, _: &V, _: &N, _: &N) -> V
+  {
+    match self.targets.find(&i)
+    {
+      Some(target) => (*target - *position).scalar_mul(&self.stiffness),
+      None         => Zero::zero()
+    }
+  }
+}
+
+/// Force given by a function of the position, the velocity and the time of each point.
+pub struct ClosureField<N, V>
+{
+  f: @fn(&V, &V, N) -> V
+}
+
+impl<N, V> ClosureField<N, V>
+{
+  pub fn new(f: @fn(&V, &V, N) -> V) -> ClosureField<N, V>
+  { ClosureField { f: f } }
+}
+
+impl<N: Clone, V> ForceField<N, V> for ClosureField<N, V>
+{
+  fn force(&self, _: uint, position: &V, velocity: &V, _: &N, t: &N) -> V
+  { (self.f)(position, velocity, t.clone()) }
+}
@@ -1,8 +1,10 @@
 extern mod extra;
 extern mod nalgebra;
-extern mod kiss3d;
 
 use std::vec;
+use std::num::Zero;
+use std::hashmap::{HashMap, HashSet};
+use extra::sort;
 use nalgebra::vec::Vec3;
 use node::Node;
 use edge::Edge;
@@ -66,6 +68,7 @@ impl<T> Blob<T>
   }
 }
 
+#[deriving(Clone)]
 pub struct Mesh
 {
   vbuff: ~[Vec3f],
@@ -82,6 +85,337 @@ impl Mesh
       ibuff: ib
     }
   }
+
+  /// Loops of edges belonging to only one triangle, each one given as an ordered list of vertices.
+  pub fn boundary_loops(&self) -> ~[~[uint]]
+  {
+    let mut edge_count: HashMap<(u32, u32), uint> = HashMap::new();
+
+    for &(a, b, c) in self.ibuff.iter()
+    {
+      for &(e1, e2) in [(a, b), (b, c), (c, a)].iter()
+      {
+        let count = edge_count.find_or_insert(if e1 < e2 { (e1, e2) } else { (e2, e1) }, 0);
+        *count = *count + 1;
+      }
+    }
+
+    // follow the orientation of the triangles along the boundary
+    let mut next:   HashMap<u32, u32> = HashMap::new();
+    let mut starts: ~[u32]            = ~[];
+
+    for &(a, b, c) in self.ibuff.iter()
+    {
+      for &(e1, e2) in [(a, b), (b, c), (c, a)].iter()
+      {
+        if *edge_count.get(&(if e1 < e2 { (e1, e2) } else { (e2, e1) })) == 1
+        {
+          next.insert(e1, e2);
+          starts.push(e1);
+        }
+      }
+    }
+
+    sort::quick_sort3(starts);
+
+    let mut visited: HashSet<u32> = HashSet::new();
+    let mut loops = ~[];
+
+    for start in starts.iter()
+    {
+      if visited.contains(start)
+      { loop }
+
+      let mut boundary = ~[];
+      let mut curr     = *start;
+
+      while visited.insert(curr)
+      {
+        boundary.push(curr as uint);
+
+        match next.find(&curr)
+        {
+          Some(n) => curr = *n,
+          None    => break
+        }
+      }
+
+      loops.push(boundary);
+    }
+
+    loops
+  }
+
+  /// For each edge shared by two triangles: its two vertices, followed by the vertex opposite to
+  /// the edge in each triangle.
+  pub fn bending_pairs(&self) -> ~[(uint, uint, uint, uint)]
+  {
+    let mut opposites: HashMap<(u32, u32), ~[u32]> = HashMap::new();
+    let mut edges:     ~[(u32, u32)]               = ~[];
+
+    for &(a, b, c) in self.ibuff.iter()
+    {
+      for &(e1, e2, o) in [(a, b, c), (b, c, a), (c, a, b)].iter()
+      {
+        let key = if e1 < e2 { (e1, e2) } else { (e2, e1) };
+        let os  = opposites.find_or_insert(key, ~[]);
+
+        if os.is_empty()
+        { edges.push(key) }
+
+        os.push(o);
+      }
+    }
+
+    let mut res = ~[];
+
+    for key in edges.iter()
+    {
+      let os = opposites.get(key);
+
+      if os.len() == 2
+      {
+        let (e1, e2) = *key;
+
+        res.push((e1 as uint, e2 as uint, os[0] as uint, os[1] as uint));
+      }
+    }
+
+    res
+  }
+}
+
+/// Groups the bending constraints so that no two constraints of a group share a vertex. Returns
+/// the indices of the constraints of each group.
+pub fn color_bending_pairs(pairs: &[(uint, uint, uint, uint)]) -> ~[~[uint]]
+{
+  if pairs.is_empty()
+  { return ~[] }
+
+  let mut nodes: ~[@mut Node<uint>] = ~[];
+  let mut incident: HashMap<uint, ~[uint]> = HashMap::new();
+
+  for (i, &(e1, e2, o1, o2)) in pairs.iter().enumerate()
+  {
+    nodes.push(@mut Node::new(i, i, Zero::zero()));
+
+    for v in [e1, e2, o1, o2].iter()
+    { incident.find_or_insert(*v, ~[]).push(i) }
+  }
+
+  for (_, cs) in incident.iter()
+  {
+    for c1 in cs.iter()
+    {
+      for c2 in cs.iter()
+      { Node::connect(nodes[*c1], nodes[*c2]) }
+    }
+  }
+
+  let nb_colors = Node::color_graph(nodes);
+  let mut groups = vec::from_elem(nb_colors as uint, ~[]);
+
+  for n in nodes.iter()
+  { groups[n.color()].push(n.content) }
+
+  groups
+}
+
+/// Split of a vertex between the groups of its triangles which are no longer linked by an edge.
+/// Used by the tearing of the surface.
+pub struct FanSplit
+{
+  priv vertex:   uint,
+  // triangles of each group, and the other vertices of these triangles
+  priv groups:   ~[~[uint]],
+  priv vertices: ~[~[uint]],
+  /// Vertex used by each group: the first group keeps the split vertex, the others use new
+  /// vertices numbered from `first_copy` (see `FanSplit::new`).
+  ids:           ~[uint]
+}
+
+impl FanSplit
+{
+  /// Groups the triangles containing `v`: two triangles stay together if they share an edge
+  /// `(v, x)` with `x` in `linked`. Returns `None` if all the triangles stay together.
+  pub fn new(triangles: &[(uint, uint, uint)], v: uint, linked: &HashSet<uint>, first_copy: uint)
+             -> Option<FanSplit>
+  {
+    let fan: ~[uint] = range(0u, triangles.len()).filter(|t| {
+      let (a, b, c) = triangles[*t];
+
+      a == v || b == v || c == v
+    }).collect();
+
+    if fan.len() < 2
+    { return None }
+
+    // connected components of the fan
+    let mut groups: ~[~[uint]] = ~[];
+    let mut visited = HashSet::new();
+
+    for t in fan.iter()
+    {
+      if !visited.insert(*t)
+      { loop }
+
+      let mut group = ~[*t];
+      let mut head  = 0u;
+
+      while head != group.len()
+      {
+        let (x1, x2) = others(triangles[group[head]], v);
+
+        for t2 in fan.iter()
+        {
+          if visited.contains(t2)
+          { loop }
+
+          let (y1, y2) = others(triangles[*t2], v);
+
+          if [x1, x2].iter().any_(|x| (*x == y1 || *x == y2) && linked.contains(x))
+          {
+            visited.insert(*t2);
+            group.push(*t2);
+          }
+        }
+
+        head = head + 1;
+      }
+
+      groups.push(group);
+    }
+
+    if groups.len() < 2
+    { return None }
+
+    let vertices = groups.iter().transform(|g| {
+      let mut vs = ~[];
+
+      for t in g.iter()
+      {
+        let (x1, x2) = others(triangles[*t], v);
+
+        vs.push(x1);
+        vs.push(x2);
+      }
+
+      vs
+    }).collect();
+
+    let mut ids = ~[v];
+
+    for i in range(first_copy, first_copy + groups.len() - 1)
+    { ids.push(i) }
+
+    Some(FanSplit { vertex: v, groups: groups, vertices: vertices, ids: ids })
+  }
+
+  /// Number of groups, i.e. of copies of the vertex after the split (including itself).
+  pub fn len(&self) -> uint
+  { self.groups.len() }
+
+  /// Copy of the vertex used by the group of triangles containing both `x` and `y`. If there is
+  /// none, the copy of the group having the vertex closest to `x`, as given by `sqdist`.
+  pub fn copy_of<N: Orderable + Bounded>(&self, x: uint, y: uint, sqdist: &fn(uint, uint) -> N) -> uint
+  {
+    match self.vertices.iter().position_(|vs| vs.contains(&x) && vs.contains(&y))
+    {
+      Some(g) => self.ids[g],
+      None    =>
+      {
+        let mut best  = 0u;
+        let mut bestd = Bounded::max_value::<N>();
+
+        for (g, vs) in self.vertices.iter().enumerate()
+        {
+          for z in vs.iter()
+          {
+            let d = sqdist(*z, x);
+
+            if d < bestd
+            {
+              bestd = d;
+              best  = g;
+            }
+          }
+        }
+
+        self.ids[best]
+      }
+    }
+  }
+
+  /// Replaces the split vertex in the bending constraint `ids` (as given by
+  /// `Mesh::bending_pairs`). The constraint follows the triangle containing the vertex: both of
+  /// them when the vertex is on the shared edge, which is intact.
+  pub fn split_bending<N: Orderable + Bounded>(&self,
+                                               ids:    (uint, uint, uint, uint),
+                                               sqdist: &fn(uint, uint) -> N)
+                                               -> (uint, uint, uint, uint)
+  {
+    let v                = self.vertex;
+    let (e0, e1, o1, o2) = ids;
+
+    if e0 == v
+    { (self.copy_of(e1, o1, sqdist), e1, o1, o2) }
+    else if e1 == v
+    { (e0, self.copy_of(e0, o1, sqdist), o1, o2) }
+    else if o1 == v
+    { (e0, e1, self.copy_of(e0, e1, sqdist), o2) }
+    else if o2 == v
+    { (e0, e1, o1, self.copy_of(e0, e1, sqdist)) }
+    else
+    { ids }
+  }
+
+  /// Replaces the split vertex in an element `(a, b, c)` lying on one of the triangles.
+  pub fn split_element<N: Orderable + Bounded>(&self,
+                                               ids:    (uint, uint, uint),
+                                               sqdist: &fn(uint, uint) -> N)
+                                               -> (uint, uint, uint)
+  {
+    let v         = self.vertex;
+    let (a, b, c) = ids;
+
+    if a == v
+    { (self.copy_of(b, c, sqdist), b, c) }
+    else if b == v
+    { (a, self.copy_of(a, c, sqdist), c) }
+    else if c == v
+    { (a, b, self.copy_of(a, b, sqdist)) }
+    else
+    { ids }
+  }
+
+  /// Replaces the split vertex in the triangles of each group.
+  pub fn split_triangles(&self, triangles: &mut [(uint, uint, uint)])
+  {
+    let v = self.vertex;
+
+    for (group, new) in self.groups.iter().zip(self.ids.iter())
+    {
+      for t in group.iter()
+      {
+        let (a, b, c) = triangles[*t];
+
+        triangles[*t] = (if a == v { *new } else { a },
+                         if b == v { *new } else { b },
+                         if c == v { *new } else { c });
+      }
+    }
+  }
+}
+
+// The two other vertices of a triangle containing `v`, in order.
+fn others(t: (uint, uint, uint), v: uint) -> (uint, uint)
+{
+  match t
+  {
+    (a, b, c) if a == v => (b, c),
+    (a, b, c) if b == v => (c, a),
+    (a, b, _)           => (a, b)
+  }
 }
 
 #[deriving(Clone)]
@@ -258,6 +592,25 @@ impl Graph
     color_groups
   }
 
+  /// The vertices of the edges of each blob.
+  pub fn export_blob_vertices(&self) -> ~[~[uint]]
+  {
+    self.blobs.iter().transform(|blob| {
+      let mut ids = ~[];
+
+      for e in blob.content.sub_nodes.iter()
+      {
+        for id in [e.content.node_1.index(), e.content.node_2.index()].iter()
+        {
+          if !ids.contains(id)
+          { ids.push(*id) }
+        }
+      }
+
+      ids
+    }).collect()
+  }
+
   pub fn export_edges(&self) -> ~[~[Edge]]
   {
     let mut color_groups = vec::from_elem(self.edge_chrom_nb as uint, ~[]);
@@ -0,0 +1,233 @@
+use std::vec;
+use std::num::{Zero, One};
+use nalgebra::traits::division_ring::DivisionRing;
+use nalgebra::traits::norm::Norm;
+use nalgebra::traits::dot::Dot;
+use nalgebra::traits::cross::Cross;
+use nalgebra::traits::dim::Dim;
+use nalgebra::traits::indexable::Indexable;
+use nalgebra::traits::vector_space::VectorSpace;
+use soft_body::SoftBody;
+
+// Stiffness matrix block of a spring between `i` and `j`: `a * n * n^t + b * Id`. The full
+// matrix has this block (negated) on the diagonal entries `(i, i)` and `(j, j)` and on the
+// off-diagonal entries `(i, j)` and `(j, i)`.
+struct SpringBlock<N, V>
+{
+  i: uint,
+  j: uint,
+  n: V,
+  a: N,
+  b: N
+}
+
+impl<N: DivisionRing + Clone, V: VectorSpace<N> + Dot<N> + Clone> SpringBlock<N, V>
+{
+  fn apply(&self, x: &V) -> V
+  { self.n.scalar_mul(&(self.a * self.n.dot(x))) + x.scalar_mul(&self.b) }
+}
+
+impl<V: VectorSpace<N> + Dot<N> + Norm<N> + Cross<V> + Indexable<uint, N> + Dim + Clone + ToStr,
+     N:  DivisionRing + Orderable + NumCast + Signed + Bounded + Round + Trigonometric + Ord + ToStr + Eq + Clone>
+     SoftBody<N, V>
+{
+  /// Backward Euler integration of the springs: solves `(M - dt^2 K) dv = dt (f + dt K v)` with a
+  /// Jacobi-preconditioned conjugate gradient, `K` being the Jacobian of the spring forces. The
+  /// spring stiffness is used as the spring constant. Contacts, bending and volume constraints are then
+  /// resolved on the new velocities.
+  pub fn solve_implicit(&mut self, dt: N)
+  {
+    let fext_dt = self.ext_forces.scalar_mul(&dt);
+    let dt2     = dt * dt;
+
+    // velocities after the explicit part of the integration
+    let velocities: ~[V] = self.points.iter().transform(|p| {
+      if p.invmass.is_zero() { p.velocity.clone() } else { p.velocity + fext_dt }
+    }).collect();
+
+    let blocks = self.spring_blocks();
+
+    // right hand side
+    let mut rhs = vec::from_elem(self.points.len(), Zero::zero::<V>());
+
+    for (c, blk) in self.constraints.iter().zip(blocks.iter())
+    {
+      let length = (self.points[c.rb1].position - self.points[c.rb2].position).norm();
+
+      if length.is_zero()
+      { loop }
+
+      let force  = blk.n.scalar_mul(&(c.stiffness * (c.rest_length - length)));
+      let r      = force.scalar_mul(&dt) - blk.apply(&(velocities[blk.i] - velocities[blk.j])).scalar_mul(&dt2);
+
+      rhs[blk.i] = rhs[blk.i] + r;
+      rhs[blk.j] = rhs[blk.j] - r;
+    }
+
+    // Jacobi preconditioner: inverse of the diagonal of the system
+    let mut diag: ~[V] = self.points.iter().transform(|p| {
+      let mut d = Zero::zero::<V>();
+
+      if !p.invmass.is_zero()
+      {
+        for k in range(0u, Dim::dim::<V>())
+        { d.set(k, One::one::<N>() / p.invmass) }
+      }
+
+      d
+    }).collect();
+
+    for blk in blocks.iter()
+    {
+      for k in range(0u, Dim::dim::<V>())
+      {
+        let nk = blk.n.at(k);
+        let dk = (blk.a * nk * nk + blk.b) * dt2;
+
+        diag[blk.i].set(k, diag[blk.i].at(k) + dk);
+        diag[blk.j].set(k, diag[blk.j].at(k) + dk);
+      }
+    }
+
+    let dvs = self.conjugate_gradient(blocks, rhs, diag, &dt2);
+
+    for (p, dv) in self.points.mut_iter().zip(dvs.iter())
+    { p.velocity = p.velocity + *dv }
+
+    self.solve_velocity_constraints(dt, false);
+  }
+
+  // Linearized springs at the current positions.
+  fn spring_blocks(&self) -> ~[SpringBlock<N, V>]
+  {
+    let mut blocks = ~[];
+
+    for c in self.constraints.iter()
+    {
+      let n      = self.points[c.rb1].position - self.points[c.rb2].position;
+      let length = n.norm();
+
+      // degenerate springs have no direction: they are left out of the system
+      if length.is_zero()
+      {
+        blocks.push(SpringBlock { i: c.rb1, j: c.rb2, n: n, a: Zero::zero(), b: Zero::zero() });
+        loop
+      }
+
+      let n = n.scalar_div(&length);
+
+      // compressed springs would make the system indefinite: their transverse stiffness is dropped
+      let s = (One::one::<N>() - c.rest_length / length).max(&Zero::zero());
+
+      blocks.push(SpringBlock {
+        i: c.rb1,
+        j: c.rb2,
+        n: n,
+        a: c.stiffness * (One::one::<N>() - s),
+        b: c.stiffness * s
+      });
+    }
+
+    blocks
+  }
+
+  // Solves `(M + dt^2 K) x = rhs` for the free points, `K` being the assembled spring blocks.
+  // Pinned points are filtered out of the residual and keep `x = 0`.
+  fn conjugate_gradient(&self, blocks: &[SpringBlock<N, V>], rhs: &[V], diag: &[V], dt2: &N) -> ~[V]
+  {
+    let tolerance: N = NumCast::from::<N, float>(1.0e-10);
+    let npoints      = self.points.len();
+
+    let precondition = |r: &[V]| -> ~[V] {
+      let mut z = ~[];
+
+      for (ri, di) in r.iter().zip(diag.iter())
+      {
+        let mut zi = Zero::zero::<V>();
+
+        for k in range(0u, Dim::dim::<V>())
+        {
+          if !di.at(k).is_zero()
+          { zi.set(k, ri.at(k) / di.at(k)) }
+        }
+
+        z.push(zi);
+      }
+
+      z
+    };
+
+    let product = |x: &[V]| -> ~[V] {
+      let mut y: ~[V] = self.points.iter().zip(x.iter()).transform(|(p, xi)| {
+        if p.invmass.is_zero() { Zero::zero() } else { xi.scalar_div(&p.invmass) }
+      }).collect();
+
+      for blk in blocks.iter()
+      {
+        let f = blk.apply(&(x[blk.i] - x[blk.j])).scalar_mul(dt2);
+
+        y[blk.i] = y[blk.i] + f;
+        y[blk.j] = y[blk.j] - f;
+      }
+
+      for (p, yi) in self.points.iter().zip(y.mut_iter())
+      {
+        if p.invmass.is_zero()
+        { *yi = Zero::zero() }
+      }
+
+      y
+    };
+
+    let dot = |a: &[V], b: &[V]| -> N {
+      let mut res = Zero::zero::<N>();
+
+      for (ai, bi) in a.iter().zip(b.iter())
+      { res = res + ai.dot(bi) }
+
+      res
+    };
+
+    let mut x = vec::from_elem(npoints, Zero::zero::<V>());
+    let mut r: ~[V] = self.points.iter().zip(rhs.iter()).transform(|(p, bi)| {
+      if p.invmass.is_zero() { Zero::zero() } else { bi.clone() }
+    }).collect();
+
+    let threshold = dot(r, r) * tolerance;
+    let mut z     = precondition(r);
+    let mut d     = z.clone();
+    let mut rz    = dot(r, z);
+
+    for _ in range(0u, self.iterations)
+    {
+      if dot(r, r) <= threshold
+      { break }
+
+      let q     = product(d);
+      let dq    = dot(d, q);
+
+      if dq.is_zero()
+      { break }
+
+      let alpha = rz / dq;
+
+      for i in range(0u, npoints)
+      {
+        x[i] = x[i] + d[i].scalar_mul(&alpha);
+        r[i] = r[i] - q[i].scalar_mul(&alpha);
+      }
+
+      z = precondition(r);
+
+      let rz_new = dot(r, z);
+      let beta   = rz_new / rz;
+
+      rz = rz_new;
+
+      for i in range(0u, npoints)
+      { d[i] = z[i] + d[i].scalar_mul(&beta) }
+    }
+
+    x
+  }
+}
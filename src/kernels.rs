@@ -2,6 +2,7 @@ use std::num::Zero;
 use nalgebra::traits::scalar_op::ScalarMul;
 use nalgebra::traits::dot::Dot;
 use nalgebra::traits::norm::Norm;
+use nalgebra::traits::cross::Cross;
 use rs2cl::kernel::Kernel;
 use rs2cl::nalgebra2cl::CLVec3f64;
 use rs2cl::pragma;
@@ -20,6 +21,9 @@ pub fn integration_kernel() -> ~str
   let fext         = k.param::<CLVec3f64>(expr::Const);
   let dt           = k.param::<f64>(expr::Const);
   let num_elements = k.param::<i32>(expr::Const);
+  let drag         = k.param::<f64>(expr::Const);
+  let dampings     = k.param::<~[f64]>(expr::Global);
+  let accs         = k.param::<~[CLVec3f64]>(expr::Global);
 
   let id = k.var::<i32>();
 
@@ -29,7 +33,10 @@ pub fn integration_kernel() -> ~str
   {
     do k.if_(invmasses[id].cl_gt(&expr::literal(0.0)))
     {
-      velocities[id].assign(velocities[id] + fext.scalar_mul(&dt));
+      let decay = k.var::<f64>();
+
+      decay.assign((expr::literal(1.0) - (drag + dampings[id]) * dt).clamp(&expr::literal(0.0), &expr::literal(1.0)));
+      velocities[id].assign((velocities[id] + (fext + accs[id]).scalar_mul(&dt)).scalar_mul(&decay));
       positions[id].assign(positions[id] + velocities[id].scalar_mul(&dt));
     }
   }
@@ -78,12 +85,19 @@ pub fn init_constraints_kernel() -> ~str
 
     dvel.assign(dt * ((length - rests[id]) * stiffs[id]));
 
+    // same as `SoftBody::collect_constraints`: fext only acts on the vertices with a mass.
     do k.if_(invmasses[id2].cl_gt(&expr::literal(0.0)))
     { dvel.assign(dvel - (velocities[id2] + fext.scalar_mul(&dt)).dot(&normal)); }
 
+    do k.if_(invmasses[id2].cl_le(&expr::literal(0.0)))
+    { dvel.assign(dvel - velocities[id2].dot(&normal)); }
+
     do k.if_(invmasses[id1].cl_gt(&expr::literal(0.0)))
     { dvel.assign(dvel + (velocities[id1] + fext.scalar_mul(&dt)).dot(&normal)); }
 
+    do k.if_(invmasses[id1].cl_le(&expr::literal(0.0)))
+    { dvel.assign(dvel + velocities[id1].dot(&normal)); }
+
     normals[id].assign(normal);
     objectives[id].assign(dvel);
   }
@@ -161,3 +175,187 @@ pub fn lin_pgs_solver_kernel() -> ~str
 
   k.to_str()
 }
+
+// Projects the points of the constraints of one colour strained beyond their limit, and writes
+// the excess strain of each constraint. The constraints of a colour share no vertex.
+pub fn strain_limiting_kernel() -> ~str
+{
+  let k = @mut Kernel::new(~"limit_strain");
+
+  k.enable_extension(pragma::cl_khr_fp64);
+
+  let start      = k.named_param::<i32>(~"start", expr::Const);
+  let num        = k.named_param::<i32>(~"num", expr::Const);
+  let id1s       = k.named_param::<~[i32]>(~"id1s", expr::Global);
+  let id2s       = k.named_param::<~[i32]>(~"id2s", expr::Global);
+  let positions  = k.named_param::<~[CLVec3f64]>(~"positions", expr::Global);
+  let invmasses  = k.named_param::<~[f64]>(~"invmasses", expr::Global);
+  let rests      = k.named_param::<~[f64]>(~"rests", expr::Global);
+  let limits     = k.named_param::<~[f64]>(~"limits", expr::Global);
+  let violations = k.named_param::<~[f64]>(~"violations", expr::Global);
+
+  let id = k.var::<i32>();
+
+  id.assign(k.get_global_id(0));
+
+  do k.if_(id.cl_lt(&num))
+  {
+    let i      = k.var::<i32>();
+    let id1    = k.named_var::<i32>(~"id1");
+    let id2    = k.named_var::<i32>(~"id2");
+    let normal = k.var::<CLVec3f64>();
+    let length = k.var::<f64>();
+    let excess = k.var::<f64>();
+    let wsum   = k.var::<f64>();
+
+    i.assign(start + id);
+    id1.assign(id1s[i]);
+    id2.assign(id2s[i]);
+
+    normal.assign(positions[id1] - positions[id2]);
+    length.assign(normal.norm());
+    excess.assign(length - length.clamp(&(rests[i] * (expr::literal(1.0) - limits[i]).clamp(&expr::literal(0.0), &expr::literal(1.0))),
+                                        &(rests[i] * (expr::literal(1.0) + limits[i]))));
+    wsum.assign(invmasses[id1] + invmasses[id2]);
+
+    violations[i].assign(expr::literal(0.0));
+
+    do k.if_(length.cl_gt(&expr::literal(0.0)))
+    {
+      do k.if_(wsum.cl_gt(&expr::literal(0.0)))
+      {
+        do k.if_(rests[i].cl_gt(&expr::literal(0.0)))
+        {
+          let correction = k.var::<f64>();
+
+          normal.assign(normal.normalized());
+          correction.assign(excess / wsum);
+
+          positions[id1].assign(positions[id1] - normal.scalar_mul(&(invmasses[id1] * correction)));
+          positions[id2].assign(positions[id2] + normal.scalar_mul(&(invmasses[id2] * correction)));
+          violations[i].assign(excess / rests[i]);
+        }
+      }
+    }
+  }
+
+  k.to_str()
+}
+
+// Velocity-level resolution of the dihedral bending constraints of one colour, as
+// `projection::project_velocities` does for `SoftBody::collect_bending_projections`. The
+// constraints of a colour share no vertex.
+pub fn bending_solver_kernel() -> ~str
+{
+  let k = @mut Kernel::new(~"solve_bending");
+
+  k.enable_extension(pragma::cl_khr_fp64);
+
+  let start      = k.named_param::<i32>(~"start", expr::Const);
+  let num        = k.named_param::<i32>(~"num", expr::Const);
+  let e0s        = k.named_param::<~[i32]>(~"e0s", expr::Global);
+  let e1s        = k.named_param::<~[i32]>(~"e1s", expr::Global);
+  let o1s        = k.named_param::<~[i32]>(~"o1s", expr::Global);
+  let o2s        = k.named_param::<~[i32]>(~"o2s", expr::Global);
+  let positions  = k.named_param::<~[CLVec3f64]>(~"positions", expr::Global);
+  let velocities = k.named_param::<~[CLVec3f64]>(~"velocities", expr::Global);
+  let invmasses  = k.named_param::<~[f64]>(~"invmasses", expr::Global);
+  let stiffs     = k.named_param::<~[f64]>(~"stiffs", expr::Global);
+  let rests      = k.named_param::<~[f64]>(~"rests", expr::Global);
+  let fext       = k.named_param::<CLVec3f64>(~"fext", expr::Const);
+  let dt         = k.named_param::<f64>(~"dt", expr::Const);
+
+  let id = k.var::<i32>();
+
+  id.assign(k.get_global_id(0));
+
+  do k.if_(id.cl_lt(&num))
+  {
+    let i   = k.var::<i32>();
+    let ids = [k.named_var::<i32>(~"e0"), k.named_var::<i32>(~"e1"),
+               k.named_var::<i32>(~"o1"), k.named_var::<i32>(~"o2")];
+
+    i.assign(start + id);
+    ids[0].assign(e0s[i]);
+    ids[1].assign(e1s[i]);
+    ids[2].assign(o1s[i]);
+    ids[3].assign(o2s[i]);
+
+    let (e0, e1, o1, o2) = (positions[ids[0]], positions[ids[1]], positions[ids[2]], positions[ids[3]]);
+
+    let e   = k.var::<CLVec3f64>();
+    let len = k.var::<f64>();
+    let n1  = k.var::<CLVec3f64>();
+    let n2  = k.var::<CLVec3f64>();
+    let sq1 = k.var::<f64>();
+    let sq2 = k.var::<f64>();
+
+    e.assign(e1 - e0);
+    len.assign(e.norm());
+    n1.assign((o1 - e0).cross(&(o1 - e1)));
+    n2.assign((o2 - e1).cross(&(o2 - e0)));
+    sq1.assign(n1.dot(&n1));
+    sq2.assign(n2.dot(&n2));
+
+    // constraints of null stiffness and degenerate triangles are skipped, as on the CPU
+    do k.if_(stiffs[i].cl_gt(&expr::literal(0.0)))
+    {
+      do k.if_(len.cl_gt(&expr::literal(0.0)))
+      {
+        do k.if_(sq1.cl_gt(&expr::literal(0.0)))
+        {
+          do k.if_(sq2.cl_gt(&expr::literal(0.0)))
+          {
+            let angle = k.var::<f64>();
+            let a     = k.var::<CLVec3f64>();
+            let b     = k.var::<CLVec3f64>();
+            let grads = [k.var::<CLVec3f64>(), k.var::<CLVec3f64>(), k.var::<CLVec3f64>(), k.var::<CLVec3f64>()];
+
+            angle.assign(n2.normalized().cross(&n1.normalized()).dot(&e.scalar_mul(&(expr::literal(1.0) / len)))
+                                                                .atan2(&n1.normalized().dot(&n2.normalized())));
+
+            a.assign(n1.scalar_mul(&(expr::literal(1.0) / sq1)));
+            b.assign(n2.scalar_mul(&(expr::literal(1.0) / sq2)));
+
+            grads[0].assign(a.scalar_mul(&((o1 - e1).dot(&e) / len)) + b.scalar_mul(&((o2 - e1).dot(&e) / len)));
+            grads[1].assign(a.scalar_mul(&(expr::literal(-1.0) * (o1 - e0).dot(&e) / len)) -
+                            b.scalar_mul(&((o2 - e0).dot(&e) / len)));
+            grads[2].assign(a.scalar_mul(&len));
+            grads[3].assign(b.scalar_mul(&len));
+
+            let objective = k.var::<f64>();
+            let denom     = k.var::<f64>();
+
+            objective.assign(dt * stiffs[i] * (angle - rests[i]));
+            denom.assign(expr::literal(0.0));
+
+            for v in range(0u, 4)
+            {
+              // a pinned vertex only adds its own velocity to the objective, and is not moved.
+              do k.if_(invmasses[ids[v]].cl_gt(&expr::literal(0.0)))
+              {
+                objective.assign(objective + grads[v].dot(&(velocities[ids[v]] + fext.scalar_mul(&dt))));
+                denom.assign(denom + invmasses[ids[v]] * grads[v].dot(&grads[v]));
+              }
+
+              do k.if_(invmasses[ids[v]].cl_le(&expr::literal(0.0)))
+              { objective.assign(objective + grads[v].dot(&velocities[ids[v]])); }
+            }
+
+            do k.if_(denom.cl_gt(&expr::literal(0.0)))
+            {
+              let lambda = k.var::<f64>();
+
+              lambda.assign(objective / denom);
+
+              for v in range(0u, 4)
+              { velocities[ids[v]].assign(velocities[ids[v]] - grads[v].scalar_mul(&(invmasses[ids[v]] * lambda))); }
+            }
+          }
+        }
+      }
+    }
+  }
+
+  k.to_str()
+}
@@ -0,0 +1,897 @@
+use std::io;
+use std::str;
+use std::cast;
+use nalgebra::vec::Vec3;
+use graph::Mesh;
+use tet_mesh::TetMesh;
+
+type Vec3f = Vec3<f32>;
+
+macro_rules! try_load(
+  ($e: expr) => (match $e { Ok(v) => v, Err(e) => return Err(e) })
+)
+
+/// Loads a triangle mesh, choosing the format from the file extension.
+pub fn load(path: &Path) -> Result<Mesh, ~str>
+{
+  let name = path.to_str();
+
+  if name.ends_with(".obj") || name.ends_with(".OBJ")
+  { load_obj(path) }
+  else if name.ends_with(".ply") || name.ends_with(".PLY")
+  { load_ply(path) }
+  else
+  { Err("Unsupported mesh format: " + name) }
+}
+
+/*
+ * Wavefront OBJ
+ */
+pub fn load_obj(path: &Path) -> Result<Mesh, ~str>
+{
+  let content = try_load!(io::read_whole_file_str(path));
+
+  parse_obj(content)
+}
+
+pub fn parse_obj(content: &str) -> Result<Mesh, ~str>
+{
+  let mut vertices:  ~[Vec3f]           = ~[];
+  let mut triangles: ~[(u32, u32, u32)] = ~[];
+  let mut face_lines: ~[uint]           = ~[];
+
+  for (l, line) in content.any_line_iter().enumerate()
+  {
+    let mut words = line.word_iter();
+
+    match words.next()
+    {
+      Some(kw) if kw == "v" =>
+      {
+        let coords: ~[&str] = words.collect();
+
+        if coords.len() < 3
+        { return Err(obj_error(l, "a vertex must have three coordinates.")) }
+
+        let mut v = [0.0f32, ..3];
+
+        for i in range(0u, 3)
+        {
+          match from_str::<f32>(coords[i])
+          {
+            Some(c) => v[i] = c,
+            None    => return Err(obj_error(l, "invalid vertex coordinate: " + coords[i]))
+          }
+        }
+
+        vertices.push(Vec3::new(v[0], v[1], v[2]));
+      },
+      Some(kw) if kw == "f" =>
+      {
+        let corners: ~[&str] = words.collect();
+
+        if corners.len() != 3
+        {
+          return Err(obj_error(l, "only triangular faces are supported, found a face with "
+                                  + corners.len().to_str() + " vertices."))
+        }
+
+        let mut ids = [0u32, ..3];
+
+        for (i, corner) in corners.iter().enumerate()
+        {
+          // only the position index matters: `v`, `v/vt`, `v//vn` and `v/vt/vn` are all valid.
+          let vid = corner.split_iter('/').next().unwrap();
+
+          match from_str::<int>(vid)
+          {
+            Some(id) if id > 0 => ids[i] = (id - 1) as u32,
+            Some(id) if id < 0 =>
+            {
+              // negative indices are relative to the vertices read so far.
+              let abs = vertices.len() as int + id;
+
+              if abs < 0
+              { return Err(obj_error(l, "vertex index out of range: " + vid)) }
+
+              ids[i] = abs as u32
+            },
+            _ => return Err(obj_error(l, "invalid vertex index: " + vid))
+          }
+        }
+
+        triangles.push((ids[0], ids[1], ids[2]));
+        face_lines.push(l);
+      },
+      _ => { } // comments, normals, texture coordinates, groups, materials…
+    }
+  }
+
+  if vertices.is_empty()
+  { return Err(~"The OBJ file does not contain any vertex.") }
+
+  for (t, &(a, b, c)) in triangles.iter().enumerate()
+  {
+    let n = vertices.len() as u32;
+
+    if a >= n || b >= n || c >= n
+    { return Err(obj_error(face_lines[t], "vertex index out of range.")) }
+  }
+
+  Ok(Mesh::new(vertices, triangles))
+}
+
+fn obj_error(line: uint, msg: &str) -> ~str
+{ "OBJ line " + (line + 1).to_str() + ": " + msg }
+
+/*
+ * Stanford PLY (ascii, binary little endian and binary big endian)
+ */
+#[deriving(Eq)]
+enum PlyFormat
+{
+  PlyAscii,
+  PlyBinaryLittleEndian,
+  PlyBinaryBigEndian
+}
+
+#[deriving(Eq, Clone)]
+enum PlyType
+{
+  PlyInt8,
+  PlyUInt8,
+  PlyInt16,
+  PlyUInt16,
+  PlyInt32,
+  PlyUInt32,
+  PlyFloat32,
+  PlyFloat64
+}
+
+enum PlyProperty
+{
+  PlyScalar(~str, PlyType),
+  PlyList(~str, PlyType, PlyType)
+}
+
+struct PlyElement
+{
+  name:       ~str,
+  count:      uint,
+  properties: ~[PlyProperty]
+}
+
+pub fn load_ply(path: &Path) -> Result<Mesh, ~str>
+{
+  let content = try_load!(io::read_whole_file(path));
+
+  parse_ply(content)
+}
+
+pub fn parse_ply(content: &[u8]) -> Result<Mesh, ~str>
+{
+  let (header_end, data_start) = match find_end_header(content)
+  {
+    Some(bounds) => bounds,
+    None         => return Err(~"PLY: missing `end_header`.")
+  };
+
+  let header = str::from_bytes(content.slice(0, header_end));
+  let (format, elements) = try_load!(parse_ply_header(header));
+
+  let data   = content.slice(data_start, content.len());
+  let text   = if format == PlyAscii { str::from_bytes(data) } else { ~"" };
+  let tokens: ~[&str] = text.word_iter().collect();
+  let mut pos = 0u;
+
+  let mut vertices:  ~[Vec3f]           = ~[];
+  let mut triangles: ~[(u32, u32, u32)] = ~[];
+  let mut has_vertex_element = false;
+
+  for elem in elements.iter()
+  {
+    let is_vertex = elem.name.as_slice() == "vertex";
+    let is_face   = elem.name.as_slice() == "face";
+
+    if is_vertex
+    {
+      has_vertex_element = true;
+
+      for coord in ["x", "y", "z"].iter()
+      {
+        if !elem.properties.iter().any_(|p| match *p { PlyScalar(ref n, _) => n.as_slice() == *coord, _ => false })
+        { return Err("PLY: vertices have no `" + *coord + "` property.") }
+      }
+    }
+
+    for _ in range(0u, elem.count)
+    {
+      let mut v = [0.0f32, ..3];
+
+      for prop in elem.properties.iter()
+      {
+        match *prop
+        {
+          PlyScalar(ref name, ty) =>
+          {
+            let val = try_load!(read_ply_value(format, tokens, data, &mut pos, ty));
+
+            if is_vertex
+            {
+              match name.as_slice()
+              {
+                "x" => v[0] = val as f32,
+                "y" => v[1] = val as f32,
+                "z" => v[2] = val as f32,
+                _   => { }
+              }
+            }
+          },
+          PlyList(ref name, count_ty, item_ty) =>
+          {
+            let count = try_load!(read_ply_value(format, tokens, data, &mut pos, count_ty)) as uint;
+            let mut items = ~[];
+
+            for _ in range(0u, count)
+            { items.push(try_load!(read_ply_value(format, tokens, data, &mut pos, item_ty))) }
+
+            if is_face && (name.as_slice() == "vertex_indices" || name.as_slice() == "vertex_index")
+            {
+              if count != 3
+              {
+                return Err("PLY: only triangular faces are supported, found a face with "
+                           + count.to_str() + " vertices.")
+              }
+
+              for id in items.iter()
+              {
+                if *id < 0.0
+                { return Err("PLY: negative vertex index: " + id.to_str()) }
+
+                if id.floor() != *id
+                { return Err("PLY: non-integer vertex index: " + id.to_str()) }
+              }
+
+              triangles.push((items[0] as u32, items[1] as u32, items[2] as u32));
+            }
+          }
+        }
+      }
+
+      if is_vertex
+      { vertices.push(Vec3::new(v[0], v[1], v[2])) }
+    }
+  }
+
+  if !has_vertex_element || vertices.is_empty()
+  { return Err(~"The PLY file does not contain any vertex.") }
+
+  for &(a, b, c) in triangles.iter()
+  {
+    let n = vertices.len() as u32;
+
+    if a >= n || b >= n || c >= n
+    {
+      return Err("PLY: vertex index out of range in face ("
+                 + a.to_str() + ", " + b.to_str() + ", " + c.to_str() + ").")
+    }
+  }
+
+  Ok(Mesh::new(vertices, triangles))
+}
+
+// Returns the end of the header text and the start of the data section.
+fn find_end_header(content: &[u8]) -> Option<(uint, uint)>
+{
+  let marker = "end_header".as_bytes();
+
+  if content.len() < marker.len()
+  { return None }
+
+  for i in range(0u, content.len() - marker.len() + 1)
+  {
+    if content.slice(i, i + marker.len()) == marker
+    {
+      let mut data_start = i + marker.len();
+
+      if data_start < content.len() && content[data_start] == '\r' as u8
+      { data_start = data_start + 1 }
+
+      if data_start < content.len() && content[data_start] == '\n' as u8
+      { data_start = data_start + 1 }
+
+      return Some((i, data_start))
+    }
+  }
+
+  None
+}
+
+fn parse_ply_header(header: &str) -> Result<(PlyFormat, ~[PlyElement]), ~str>
+{
+  let mut format   = None;
+  let mut elements: ~[PlyElement] = ~[];
+
+  for (l, line) in header.any_line_iter().enumerate()
+  {
+    let words: ~[&str] = line.word_iter().collect();
+
+    if l == 0
+    {
+      if words.len() != 1 || words[0] != "ply"
+      { return Err(~"PLY: missing `ply` magic number.") }
+
+      loop
+    }
+
+    if words.is_empty()
+    { loop }
+
+    match words[0]
+    {
+      "format" =>
+      {
+        if words.len() < 2
+        { return Err(~"PLY: incomplete format line.") }
+
+        format = match words[1]
+        {
+          "ascii"                => Some(PlyAscii),
+          "binary_little_endian" => Some(PlyBinaryLittleEndian),
+          "binary_big_endian"    => Some(PlyBinaryBigEndian),
+          other                  => return Err("PLY: unknown format: " + other)
+        }
+      },
+      "element" =>
+      {
+        if words.len() != 3
+        { return Err(~"PLY: invalid element declaration: " + line) }
+
+        let count = match from_str::<uint>(words[2])
+        {
+          Some(c) => c,
+          None    => return Err("PLY: invalid element count: " + words[2])
+        };
+
+        elements.push(PlyElement { name: words[1].to_owned(), count: count, properties: ~[] });
+      },
+      "property" =>
+      {
+        if elements.is_empty()
+        { return Err(~"PLY: property declared outside of an element.") }
+
+        let prop = if words.len() == 5 && words[1] == "list"
+        {
+          let count_ty = try_load!(parse_ply_type(words[2]));
+          let item_ty  = try_load!(parse_ply_type(words[3]));
+
+          PlyList(words[4].to_owned(), count_ty, item_ty)
+        }
+        else if words.len() == 3
+        { PlyScalar(words[2].to_owned(), try_load!(parse_ply_type(words[1]))) }
+        else
+        { return Err(~"PLY: invalid property declaration: " + line) };
+
+        elements[elements.len() - 1].properties.push(prop);
+      },
+      _ => { } // comment, obj_info…
+    }
+  }
+
+  match format
+  {
+    Some(f) => Ok((f, elements)),
+    None    => Err(~"PLY: missing format declaration.")
+  }
+}
+
+fn parse_ply_type(name: &str) -> Result<PlyType, ~str>
+{
+  match name
+  {
+    "char"   | "int8"    => Ok(PlyInt8),
+    "uchar"  | "uint8"   => Ok(PlyUInt8),
+    "short"  | "int16"   => Ok(PlyInt16),
+    "ushort" | "uint16"  => Ok(PlyUInt16),
+    "int"    | "int32"   => Ok(PlyInt32),
+    "uint"   | "uint32"  => Ok(PlyUInt32),
+    "float"  | "float32" => Ok(PlyFloat32),
+    "double" | "float64" => Ok(PlyFloat64),
+    other                => Err("PLY: unknown property type: " + other)
+  }
+}
+
+fn ply_type_size(ty: PlyType) -> uint
+{
+  match ty
+  {
+    PlyInt8    | PlyUInt8  => 1,
+    PlyInt16   | PlyUInt16 => 2,
+    PlyInt32   | PlyUInt32 | PlyFloat32 => 4,
+    PlyFloat64 => 8
+  }
+}
+
+fn read_ply_value(format: PlyFormat,
+                  tokens: &[&str],
+                  data:   &[u8],
+                  pos:    &mut uint,
+                  ty:     PlyType) -> Result<f64, ~str>
+{
+  match format
+  {
+    PlyAscii =>
+    {
+      if *pos >= tokens.len()
+      { return Err(~"PLY: unexpected end of file.") }
+
+      let token = tokens[*pos];
+
+      *pos = *pos + 1;
+
+      match from_str::<f64>(token)
+      {
+        Some(v) => Ok(v),
+        None    => Err("PLY: invalid value: " + token)
+      }
+    },
+    _ =>
+    {
+      let size = ply_type_size(ty);
+
+      if *pos + size > data.len()
+      { return Err(~"PLY: unexpected end of file.") }
+
+      let mut bits = 0u64;
+
+      for i in range(0u, size)
+      {
+        let byte = if format == PlyBinaryLittleEndian { data[*pos + size - 1 - i] }
+                   else                               { data[*pos + i] };
+
+        bits = (bits << 8) | (byte as u64);
+      }
+
+      *pos = *pos + size;
+
+      let val = match ty
+      {
+        PlyInt8    => (bits as u8)  as i8  as f64,
+        PlyUInt8   => (bits as u8)  as f64,
+        PlyInt16   => (bits as u16) as i16 as f64,
+        PlyUInt16  => (bits as u16) as f64,
+        PlyInt32   => (bits as u32) as i32 as f64,
+        PlyUInt32  => (bits as u32) as f64,
+        PlyFloat32 => unsafe { cast::transmute::<u32, f32>(bits as u32) as f64 },
+        PlyFloat64 => unsafe { cast::transmute::<u64, f64>(bits) }
+      };
+
+      Ok(val)
+    }
+  }
+}
+
+/*
+ * TetGen (.node and .ele)
+ */
+
+/// Loads a tetrahedral mesh from the TetGen files sharing the name of `path`, which may be the
+/// `.node` or the `.ele` file.
+pub fn load_tetgen(path: &Path) -> Result<TetMesh, ~str>
+{
+  let node = try_load!(io::read_whole_file_str(&path.with_filetype("node")));
+  let ele  = try_load!(io::read_whole_file_str(&path.with_filetype("ele")));
+
+  parse_tetgen(node, ele)
+}
+
+pub fn parse_tetgen(node: &str, ele: &str) -> Result<TetMesh, ~str>
+{
+  let node_lines = tetgen_lines(node);
+  let ele_lines  = tetgen_lines(ele);
+
+  if node_lines.is_empty()
+  { return Err(~"TetGen: the .node file has no header.") }
+
+  if ele_lines.is_empty()
+  { return Err(~"TetGen: the .ele file has no header.") }
+
+  // <# of points> <dimension (3)> <# of attributes> <boundary markers (0 or 1)>
+  let (hl, ref header) = node_lines[0];
+  let npoints = try_load!(tetgen_uint(*header, 0, ".node", hl));
+
+  if header.len() > 1 && header[1] != "3"
+  { return Err(~"TetGen: only three-dimensional points are supported.") }
+
+  if npoints == 0
+  { return Err(~"TetGen: the .node file does not contain any point.") }
+
+  if node_lines.len() < npoints + 1
+  { return Err(~"TetGen: the .node file has fewer points than announced.") }
+
+  let mut vertices = ~[];
+  let mut indices  = ~[];
+
+  for &(l, ref words) in node_lines.slice(1, npoints + 1).iter()
+  {
+    if words.len() < 4
+    { return Err(tetgen_error(".node", l, "a point must have an index and three coordinates.")) }
+
+    indices.push(try_load!(tetgen_uint(*words, 0, ".node", l)));
+
+    let mut v = [0.0f32, ..3];
+
+    for i in range(0u, 3)
+    {
+      match from_str::<f32>(words[i + 1])
+      {
+        Some(c) => v[i] = c,
+        None    => return Err(tetgen_error(".node", l, "invalid point coordinate: " + words[i + 1]))
+      }
+    }
+
+    vertices.push(Vec3::new(v[0], v[1], v[2]));
+  }
+
+  // indices are contiguous, starting at 0 or 1
+  let first = indices[0];
+
+  if first > 1
+  {
+    let (l, _) = node_lines[1];
+
+    return Err(tetgen_error(".node", l, "points must be numbered from 0 or 1."))
+  }
+
+  for (i, id) in indices.iter().enumerate()
+  {
+    let (l, _) = node_lines[i + 1];
+
+    if *id != first + i
+    { return Err(tetgen_error(".node", l, "points must be numbered consecutively.")) }
+  }
+
+  // <# of tetrahedra> <nodes per tetrahedron (4 or 10)> <region attribute (0 or 1)>
+  let (hl, ref header) = ele_lines[0];
+  let ntets = try_load!(tetgen_uint(*header, 0, ".ele", hl));
+
+  if ele_lines.len() < ntets + 1
+  { return Err(~"TetGen: the .ele file has fewer tetrahedra than announced.") }
+
+  let mut tetrahedra = ~[];
+
+  for &(l, ref words) in ele_lines.slice(1, ntets + 1).iter()
+  {
+    if words.len() < 5
+    { return Err(tetgen_error(".ele", l, "a tetrahedron must have an index and four vertices.")) }
+
+    // the corners come first for second order tetrahedra
+    let mut ids = [0u32, ..4];
+
+    for i in range(0u, 4)
+    {
+      let id = try_load!(tetgen_uint(*words, i + 1, ".ele", l));
+
+      if id < first || id - first >= npoints
+      { return Err(tetgen_error(".ele", l, "vertex index out of range: " + words[i + 1])) }
+
+      ids[i] = (id - first) as u32;
+    }
+
+    tetrahedra.push((ids[0], ids[1], ids[2], ids[3]));
+  }
+
+  if tetrahedra.is_empty()
+  { return Err(~"TetGen: the .ele file does not contain any tetrahedron.") }
+
+  Ok(TetMesh::new(vertices, tetrahedra))
+}
+
+// Non-empty lines, without their comments, split into words, with their line number.
+fn tetgen_lines<'r>(content: &'r str) -> ~[(uint, ~[&'r str])]
+{
+  let mut res = ~[];
+
+  for (l, line) in content.any_line_iter().enumerate()
+  {
+    let data           = line.split_iter('#').next().unwrap();
+    let words: ~[&str] = data.word_iter().collect();
+
+    if !words.is_empty()
+    { res.push((l, words)) }
+  }
+
+  res
+}
+
+fn tetgen_uint(words: &[&str], i: uint, file: &str, line: uint) -> Result<uint, ~str>
+{
+  if i >= words.len()
+  { return Err(tetgen_error(file, line, "missing value.")) }
+
+  match from_str::<uint>(words[i])
+  {
+    Some(v) => Ok(v),
+    None    => Err(tetgen_error(file, line, "invalid integer: " + words[i]))
+  }
+}
+
+fn tetgen_error(file: &str, line: uint, msg: &str) -> ~str
+{ "TetGen " + file + " line " + (line + 1).to_str() + ": " + msg }
+
+#[cfg(test)]
+mod test
+{
+  use std::cast;
+  use loader::{parse_obj, parse_ply, parse_tetgen};
+
+  fn obj_error(content: &str) -> ~str
+  {
+    match parse_obj(content)
+    {
+      Ok(_)  => fail!("the OBJ file should have been rejected."),
+      Err(e) => e
+    }
+  }
+
+  #[test]
+  fn parse_obj_triangles()
+  {
+    let obj = "# square\nv 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nvn 0 0 1\nf 1 2 3\nf 3/1/1 2//1 4/2\n";
+
+    match parse_obj(obj)
+    {
+      Ok(mesh) =>
+      {
+        assert!(mesh.vbuff.len() == 4);
+        assert!(mesh.ibuff == ~[(0, 1, 2), (2, 1, 3)]);
+        assert!(mesh.vbuff[3].x == 1.0 && mesh.vbuff[3].y == 1.0);
+      },
+      Err(e) => fail!(e)
+    }
+  }
+
+  #[test]
+  fn parse_obj_negative_indices()
+  {
+    match parse_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\nf -3 -2 -1\n")
+    {
+      Ok(mesh) => assert!(mesh.ibuff == ~[(0, 1, 2)]),
+      Err(e)   => fail!(e)
+    }
+  }
+
+  #[test]
+  fn parse_obj_negative_index_out_of_range()
+  {
+    assert!(obj_error("v 0 0 0\nv 1 0 0\nf -3 -2 -1\nv 0 1 0\n") ==
+            ~"OBJ line 3: vertex index out of range: -3")
+  }
+
+  #[test]
+  fn parse_obj_quad()
+  {
+    assert!(obj_error("v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nf 1 2 4 3\n") ==
+            ~"OBJ line 5: only triangular faces are supported, found a face with 4 vertices.")
+  }
+
+  #[test]
+  fn parse_obj_vertex_out_of_range()
+  {
+    assert!(obj_error("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n") ==
+            ~"OBJ line 4: vertex index out of range.")
+  }
+
+  #[test]
+  fn parse_obj_no_vertices()
+  { assert!(obj_error("# empty\nvn 0 0 1\n") == ~"The OBJ file does not contain any vertex.") }
+
+  static PLY_HEADER: &'static str = "ply\nformat ascii 1.0\nelement vertex 4\nproperty float x\nproperty float y\nproperty float z\n";
+
+  fn ply_error(content: &str) -> ~str
+  {
+    match parse_ply(content.as_bytes())
+    {
+      Ok(_)  => fail!("the PLY file should have been rejected."),
+      Err(e) => e
+    }
+  }
+
+  #[test]
+  fn parse_ascii_ply()
+  {
+    let ply = PLY_HEADER + "element face 2\nproperty list uchar int vertex_indices\nend_header\n"
+              + "0 0 0\n1 0 0\n0 1 0\n1 1 0\n3 0 1 2\n3 2 1 3\n";
+
+    match parse_ply(ply.as_bytes())
+    {
+      Ok(mesh) =>
+      {
+        assert!(mesh.vbuff.len() == 4);
+        assert!(mesh.ibuff == ~[(0, 1, 2), (2, 1, 3)]);
+        assert!(mesh.vbuff[3].x == 1.0 && mesh.vbuff[3].y == 1.0);
+      },
+      Err(e) => fail!(e)
+    }
+  }
+
+  // A triangle with the vertices (0, 0, 0), (1.5, 0, 0) and (0, -2, 0).
+  fn binary_ply(big_endian: bool) -> ~[u8]
+  {
+    let format = if big_endian { "binary_big_endian" } else { "binary_little_endian" };
+    let header = "ply\nformat " + format + " 1.0\nelement vertex 3\nproperty float x\nproperty float y\n"
+                 + "property float z\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n";
+    let mut ply = header.as_bytes().to_owned();
+
+    for c in [0.0f32, 0.0, 0.0, 1.5, 0.0, 0.0, 0.0, -2.0, 0.0].iter()
+    { push_bytes(&mut ply, unsafe { cast::transmute::<f32, u32>(*c) } as u64, 4, big_endian) }
+
+    push_bytes(&mut ply, 3, 1, big_endian);
+
+    for id in [0u64, 1, 2].iter()
+    { push_bytes(&mut ply, *id, 4, big_endian) }
+
+    ply
+  }
+
+  fn push_bytes(bytes: &mut ~[u8], bits: u64, size: uint, big_endian: bool)
+  {
+    for i in range(0u, size)
+    {
+      let shift = if big_endian { 8 * (size - 1 - i) } else { 8 * i };
+
+      bytes.push((bits >> shift) as u8)
+    }
+  }
+
+  fn check_binary_ply(content: &[u8])
+  {
+    match parse_ply(content)
+    {
+      Ok(mesh) =>
+      {
+        assert!(mesh.ibuff == ~[(0, 1, 2)]);
+        assert!(mesh.vbuff[1].x == 1.5 && mesh.vbuff[2].y == -2.0);
+      },
+      Err(e) => fail!(e)
+    }
+  }
+
+  #[test]
+  fn parse_binary_little_endian_ply()
+  { check_binary_ply(binary_ply(false)) }
+
+  #[test]
+  fn parse_binary_big_endian_ply()
+  { check_binary_ply(binary_ply(true)) }
+
+  #[test]
+  fn parse_ply_missing_end_header()
+  { assert!(ply_error(PLY_HEADER + "0 0 0\n") == ~"PLY: missing `end_header`.") }
+
+  #[test]
+  fn parse_ply_fewer_vertices_than_announced()
+  {
+    let ply = PLY_HEADER + "end_header\n0 0 0\n1 0 0\n0 1 0\n";
+
+    assert!(ply_error(ply) == ~"PLY: unexpected end of file.")
+  }
+
+  #[test]
+  fn parse_ply_quad()
+  {
+    let ply = PLY_HEADER + "element face 1\nproperty list uchar int vertex_indices\nend_header\n"
+              + "0 0 0\n1 0 0\n0 1 0\n1 1 0\n4 0 1 3 2\n";
+
+    assert!(ply_error(ply) == ~"PLY: only triangular faces are supported, found a face with 4 vertices.")
+  }
+
+  #[test]
+  fn parse_ply_vertex_out_of_range()
+  {
+    let ply = PLY_HEADER + "element face 1\nproperty list uchar int vertex_indices\nend_header\n"
+              + "0 0 0\n1 0 0\n0 1 0\n1 1 0\n3 0 1 4\n";
+
+    assert!(ply_error(ply) == ~"PLY: vertex index out of range in face (0, 1, 4).")
+  }
+
+  #[test]
+  fn parse_ply_non_integer_index()
+  {
+    let ply = PLY_HEADER + "element face 1\nproperty list uchar float vertex_indices\nend_header\n"
+              + "0 0 0\n1 0 0\n0 1 0\n1 1 0\n3 0 1.5 2\n";
+
+    assert!(ply_error(ply) == ~"PLY: non-integer vertex index: 1.5")
+  }
+
+  static TET_NODE: &'static str = "# unit tetrahedron\n4 3 0 0\n1 0 0 0\n2 1 0 0\n3 0 1 0\n4 0 0 1\n";
+
+  fn tetgen_error(node: &str, ele: &str) -> ~str
+  {
+    match parse_tetgen(node, ele)
+    {
+      Ok(_)  => fail!("the TetGen files should have been rejected."),
+      Err(e) => e
+    }
+  }
+
+  #[test]
+  fn parse_tetgen_one_based()
+  {
+    match parse_tetgen(TET_NODE, "1 4 0\n1 1 2 3 4 # first\n")
+    {
+      Ok(mesh) =>
+      {
+        assert!(mesh.vbuff.len() == 4);
+        assert!(mesh.tbuff == ~[(0, 1, 2, 3)]);
+        assert!(mesh.vbuff[3].z == 1.0);
+      },
+      Err(e) => fail!(e)
+    }
+  }
+
+  #[test]
+  fn parse_tetgen_zero_based()
+  {
+    let node = "4 3 0 0\n0 0 0 0\n1 1 0 0\n2 0 1 0\n3 0 0 1\n";
+
+    match parse_tetgen(node, "1 4 0\n0 3 2 1 0\n")
+    {
+      Ok(mesh) => assert!(mesh.tbuff == ~[(3, 2, 1, 0)]),
+      Err(e)   => fail!(e)
+    }
+  }
+
+  #[test]
+  fn parse_tetgen_fewer_points_than_announced()
+  {
+    assert!(tetgen_error("5 3 0 0\n1 0 0 0\n2 1 0 0\n", "1 4 0\n1 1 2 3 4\n") ==
+            ~"TetGen: the .node file has fewer points than announced.")
+  }
+
+  #[test]
+  fn parse_tetgen_fewer_tetrahedra_than_announced()
+  {
+    assert!(tetgen_error(TET_NODE, "2 4 0\n1 1 2 3 4\n") ==
+            ~"TetGen: the .ele file has fewer tetrahedra than announced.")
+  }
+
+  #[test]
+  fn parse_tetgen_not_consecutive()
+  {
+    let node = "4 3 0 0\n1 0 0 0\n2 1 0 0\n4 0 1 0\n5 0 0 1\n";
+
+    assert!(tetgen_error(node, "1 4 0\n1 1 2 4 5\n") ==
+            ~"TetGen .node line 4: points must be numbered consecutively.")
+  }
+
+  #[test]
+  fn parse_tetgen_vertex_out_of_range()
+  {
+    assert!(tetgen_error(TET_NODE, "1 4 0\n1 1 2 3 5\n") ==
+            ~"TetGen .ele line 2: vertex index out of range: 5")
+  }
+
+  #[test]
+  fn parse_tetgen_vertex_below_first_index()
+  {
+    assert!(tetgen_error(TET_NODE, "1 4 0\n1 0 1 2 3\n") ==
+            ~"TetGen .ele line 2: vertex index out of range: 0")
+  }
+
+  #[test]
+  fn parse_tetgen_no_points()
+  {
+    assert!(tetgen_error("0 3 0 0\n", "1 4 0\n1 1 2 3 4\n") ==
+            ~"TetGen: the .node file does not contain any point.")
+  }
+
+  #[test]
+  fn parse_tetgen_first_index()
+  {
+    let node = "4 3 0 0\n2 0 0 0\n3 1 0 0\n4 0 1 0\n5 0 0 1\n";
+
+    assert!(tetgen_error(node, "1 4 0\n1 2 3 4 5\n") ==
+            ~"TetGen .node line 2: points must be numbered from 0 or 1.")
+  }
+}
@@ -0,0 +1,356 @@
+use std::vec;
+use std::hashmap::HashMap;
+use nalgebra::traits::cross::Cross;
+use nalgebra::traits::norm::Norm;
+use graph::Mesh;
+use tet_mesh::TetMesh;
+
+/// Physical description of a region of cloth.
+#[deriving(Clone)]
+pub struct Material
+{
+  /// Mass per unit area, or per unit volume for tetrahedral meshes.
+  density:           f64,
+  /// Stiffness of the constraints along the edges of the mesh.
+  stretch_stiffness: f64,
+  /// Stiffness of the bending constraints: the constraints added between vertices at distance 2
+  /// by `Graph::augment`, or the dihedral angle constraints.
+  bend_stiffness:    f64,
+  /// Stiffness of the volume constraints on the tetrahedra of solids.
+  volume_stiffness:   f64,
+  /// Maximum strain of the edges, in absolute value, enforced by the strain limiting pass.
+  strain_limit:       f64,
+  /// Young's modulus of the membrane along the warp direction, per unit area (i.e. times the
+  /// thickness).
+  young_modulus:      f64,
+  /// Ratio of the Young's modulus along the weft direction to the one along the warp direction.
+  weft_ratio:         f64,
+  /// Poisson ratio of the membrane: contraction along the weft direction when stretched along
+  /// the warp direction.
+  poisson_ratio:      f64,
+  /// Damping coefficient of the constraints: force opposing the relative velocity of their two
+  /// vertices, per unit of velocity.
+  damping:            f64,
+  /// Mass-proportional Rayleigh damping coefficient: the velocity of the vertices decays at this
+  /// rate, per second.
+  rayleigh_mass:      f64,
+  /// Stiffness-proportional Rayleigh damping coefficient: each constraint is damped by this
+  /// coefficient times its stiffness.
+  rayleigh_stiffness: f64
+}
+
+impl Material
+{
+  pub fn new(density: f64, stretch_stiffness: f64, bend_stiffness: f64) -> Material
+  {
+    Material {
+      density:           density,
+      stretch_stiffness: stretch_stiffness,
+      bend_stiffness:    bend_stiffness,
+      volume_stiffness:   50.0,
+      strain_limit:       Bounded::max_value(),
+      young_modulus:      1000.0,
+      weft_ratio:         1.0,
+      poisson_ratio:      0.3,
+      damping:            0.0,
+      rayleigh_mass:      0.0,
+      rayleigh_stiffness: 0.0
+    }
+  }
+
+  pub fn default() -> Material
+  { Material::new(1.0, 50.0, 50.0) }
+}
+
+/// Assignment of a material to each vertex, and optionally to each triangle, of a mesh.
+///
+/// The mass of a triangle and the stretch parameters of its edges come from its own material if
+/// it has one (see `assign_faces`), so that the border between two regions stays sharp. Otherwise,
+/// and for the other elements, the parameters are combined from the materials of the vertices, so
+/// the border is blended over one ring of elements.
+pub struct MaterialMap
+{
+  materials:        ~[Material],
+  vertex_materials: ~[uint],
+  // `None` for the triangles using the materials of their vertices
+  face_materials:   ~[Option<uint>]
+}
+
+impl MaterialMap
+{
+  /// Creates a map where every vertex of `mesh` has the material `default`.
+  pub fn new(mesh: &Mesh, default: Material) -> MaterialMap
+  {
+    MaterialMap {
+      materials:        ~[default],
+      vertex_materials: vec::from_elem(mesh.vbuff.len(), 0u),
+      face_materials:   vec::from_elem(mesh.ibuff.len(), None)
+    }
+  }
+
+  /// Registers a material and returns its identifier.
+  pub fn add_material(&mut self, material: Material) -> uint
+  {
+    self.materials.push(material);
+    self.materials.len() - 1
+  }
+
+  pub fn assign_vertices(&mut self, ids: &[uint], material: uint)
+  {
+    assert!(material < self.materials.len(), "Unknown material.");
+
+    for i in ids.iter()
+    { self.vertex_materials[*i] = material }
+  }
+
+  /// Assigns `material` to the triangles `faces` of `mesh`, and to their vertices.
+  ///
+  /// The masses of the triangles and the stretch stiffness and strain limit of their edges follow
+  /// the triangles: an edge on the border of `faces` combines its two triangles (see `stiffness`
+  /// and `strain_limits`). The parameters defined per vertex (damping, bending, ...) are still
+  /// blended along the border.
+  pub fn assign_faces(&mut self, mesh: &Mesh, faces: &[uint], material: uint)
+  {
+    for f in faces.iter()
+    {
+      let (a, b, c) = mesh.ibuff[*f];
+
+      self.assign_vertices([a as uint, b as uint, c as uint], material);
+      self.face_materials[*f] = Some(material);
+    }
+  }
+
+  /// Assigns `material` to every vertex whose attribute value satisfies `pred`.
+  pub fn assign_where(&mut self, attribute: &[f64], pred: &fn(f64) -> bool, material: uint)
+  {
+    assert!(attribute.len() == self.vertex_materials.len(),
+            "The attribute must have one value per vertex.");
+
+    for (i, a) in attribute.iter().enumerate()
+    {
+      if pred(*a)
+      { self.assign_vertices([i], material) }
+    }
+  }
+
+  /// Uses a per-vertex attribute holding material identifiers.
+  pub fn assign_attribute(&mut self, attribute: &[uint])
+  {
+    assert!(attribute.len() == self.vertex_materials.len(),
+            "The attribute must have one value per vertex.");
+
+    for (i, m) in attribute.iter().enumerate()
+    { self.assign_vertices([i], *m) }
+  }
+
+  pub fn vertex_material<'r>(&'r self, i: uint) -> &'r Material
+  { &self.materials[self.vertex_materials[i]] }
+
+  /// Material of the triangle `f`, if one was assigned with `assign_faces`.
+  pub fn face_material<'r>(&'r self, f: uint) -> Option<&'r Material>
+  { self.face_materials[f].map(|m| &self.materials[*m]) }
+
+  /// Inverse masses of the vertices: each triangle distributes its mass (area times its density)
+  /// equally between its three vertices. The density of a triangle without material is the mean
+  /// density of its vertices.
+  pub fn invmasses(&self, mesh: &Mesh) -> ~[f64]
+  {
+    let mut masses = vec::from_elem(mesh.vbuff.len(), 0.0f64);
+
+    for (f, &(a, b, c)) in mesh.ibuff.iter().enumerate()
+    {
+      let e1      = mesh.vbuff[b] - mesh.vbuff[a];
+      let e2      = mesh.vbuff[c] - mesh.vbuff[a];
+      let area    = 0.5 * (e1.cross(&e2).norm() as f64);
+      let density = match self.face_material(f)
+      {
+        Some(m) => m.density,
+        None    => (self.vertex_material(a as uint).density +
+                    self.vertex_material(b as uint).density +
+                    self.vertex_material(c as uint).density) / 3.0
+      };
+      let m       = area * density / 3.0;
+
+      masses[a] = masses[a] + m;
+      masses[b] = masses[b] + m;
+      masses[c] = masses[c] + m;
+    }
+
+    // vertices without any triangle are left static.
+    masses.iter().transform(|m| if *m > 0.0 { 1.0 / *m } else { 0.0 }).collect()
+  }
+
+  /// Inverse masses of the vertices of a tetrahedral mesh: each tetrahedron distributes its mass
+  /// (volume times the mean density of its vertices) equally between its four vertices.
+  pub fn tet_invmasses(&self, tets: &TetMesh) -> ~[f64]
+  {
+    let mut masses = vec::from_elem(tets.vbuff.len(), 0.0f64);
+
+    for (&(a, b, c, d), volume) in tets.tbuff.iter().zip(tets.volumes().iter())
+    {
+      let density = (self.vertex_material(a as uint).density +
+                     self.vertex_material(b as uint).density +
+                     self.vertex_material(c as uint).density +
+                     self.vertex_material(d as uint).density) / 4.0;
+      let m       = volume.abs() * density / 4.0;
+
+      masses[a] = masses[a] + m;
+      masses[b] = masses[b] + m;
+      masses[c] = masses[c] + m;
+      masses[d] = masses[d] + m;
+    }
+
+    // vertices without any tetrahedron are left static.
+    masses.iter().transform(|m| if *m > 0.0 { 1.0 / *m } else { 0.0 }).collect()
+  }
+
+  /// Stiffness of each constraint `(ids1[i], ids2[i])`. For edges of the mesh, the mean stretch
+  /// stiffness of its triangles; a triangle without material contributes the mean of the two
+  /// vertices of the edge. For the other constraints, the mean bend stiffness of their vertices.
+  pub fn stiffness(&self, mesh: &Mesh, ids1: &[i32], ids2: &[i32]) -> ~[f64]
+  {
+    let edges = edge_faces(mesh);
+
+    ids1.iter().zip(ids2.iter()).transform(|(i1, i2)| {
+      let m1 = self.vertex_material(*i1 as uint);
+      let m2 = self.vertex_material(*i2 as uint);
+
+      match edges.find(&edge_key(*i1, *i2))
+      {
+        Some(faces) =>
+        {
+          let sum = faces.iter().fold(0.0, |sum, f| {
+            sum + match self.face_material(*f)
+            {
+              Some(m) => m.stretch_stiffness,
+              None    => 0.5 * (m1.stretch_stiffness + m2.stretch_stiffness)
+            }
+          });
+
+          sum / (faces.len() as f64)
+        },
+        None => 0.5 * (m1.bend_stiffness + m2.bend_stiffness)
+      }
+    }).collect()
+  }
+
+  /// Strain limit of each constraint `(ids1[i], ids2[i])`. For edges of the mesh, the smallest
+  /// strain limit of its triangles; a triangle without material contributes the smaller strain
+  /// limit of the two vertices of the edge. None for the other constraints.
+  pub fn strain_limits(&self, mesh: &Mesh, ids1: &[i32], ids2: &[i32]) -> ~[f64]
+  {
+    let edges = edge_faces(mesh);
+
+    ids1.iter().zip(ids2.iter()).transform(|(i1, i2)| {
+      match edges.find(&edge_key(*i1, *i2))
+      {
+        Some(faces) =>
+        {
+          let vertices = self.vertex_material(*i1 as uint).strain_limit.min(
+                           &self.vertex_material(*i2 as uint).strain_limit);
+
+          faces.iter().fold(Bounded::max_value::<f64>(), |limit, f| {
+            limit.min(&match self.face_material(*f)
+            {
+              Some(m) => m.strain_limit,
+              None    => vertices
+            })
+          })
+        },
+        None => Bounded::max_value()
+      }
+    }).collect()
+  }
+
+  /// Stiffness of the edges `(ids1[i], ids2[i])` of a tetrahedral mesh: the mean stretch
+  /// stiffness of their two vertices.
+  pub fn tet_stiffness(&self, ids1: &[i32], ids2: &[i32]) -> ~[f64]
+  {
+    ids1.iter().zip(ids2.iter()).transform(|(i1, i2)| {
+      0.5 * (self.vertex_material(*i1 as uint).stretch_stiffness +
+             self.vertex_material(*i2 as uint).stretch_stiffness)
+    }).collect()
+  }
+
+  /// Strain limit of the edges `(ids1[i], ids2[i])` of a tetrahedral mesh: the smaller strain
+  /// limit of their two vertices.
+  pub fn tet_strain_limits(&self, ids1: &[i32], ids2: &[i32]) -> ~[f64]
+  {
+    ids1.iter().zip(ids2.iter()).transform(|(i1, i2)| {
+      self.vertex_material(*i1 as uint).strain_limit.min(&self.vertex_material(*i2 as uint).strain_limit)
+    }).collect()
+  }
+
+  /// Damping coefficient of each constraint `(ids1[i], ids2[i])` of stiffness `stiffness[i]`: the
+  /// mean damping of its two vertices, plus their mean stiffness-proportional Rayleigh
+  /// coefficient times the stiffness.
+  pub fn constraint_damping(&self, ids1: &[i32], ids2: &[i32], stiffness: &[f64]) -> ~[f64]
+  {
+    let mut res = ~[];
+
+    for i in range(0u, ids1.len())
+    {
+      let m1 = self.vertex_material(ids1[i] as uint);
+      let m2 = self.vertex_material(ids2[i] as uint);
+
+      res.push(0.5 * (m1.damping + m2.damping) +
+               0.5 * (m1.rayleigh_stiffness + m2.rayleigh_stiffness) * stiffness[i]);
+    }
+
+    res
+  }
+
+  /// Mass-proportional Rayleigh damping coefficient of each vertex.
+  pub fn point_damping(&self) -> ~[f64]
+  { self.vertex_materials.iter().transform(|m| self.materials[*m].rayleigh_mass).collect() }
+
+  /// Stiffness of each dihedral bending constraint: the mean bend stiffness of its four vertices.
+  pub fn bending_stiffness(&self, pairs: &[(uint, uint, uint, uint)]) -> ~[f64]
+  {
+    pairs.iter().transform(|&(e1, e2, o1, o2)| {
+      (self.vertex_material(e1).bend_stiffness + self.vertex_material(e2).bend_stiffness +
+       self.vertex_material(o1).bend_stiffness + self.vertex_material(o2).bend_stiffness) / 4.0
+    }).collect()
+  }
+
+  /// Stiffness of the volume constraint of each tetrahedron: the mean volume stiffness of its
+  /// four vertices.
+  pub fn volume_stiffness(&self, tetrahedra: &[(uint, uint, uint, uint)]) -> ~[f64]
+  {
+    tetrahedra.iter().transform(|&(a, b, c, d)| {
+      (self.vertex_material(a).volume_stiffness + self.vertex_material(b).volume_stiffness +
+       self.vertex_material(c).volume_stiffness + self.vertex_material(d).volume_stiffness) / 4.0
+    }).collect()
+  }
+
+  /// Young's moduli along the warp and the weft directions and Poisson ratio of the membrane
+  /// element of each triangle: the means over its three vertices.
+  pub fn membrane_parameters(&self, triangles: &[(uint, uint, uint)]) -> ~[(f64, f64, f64)]
+  {
+    triangles.iter().transform(|&(a, b, c)| {
+      let (ma, mb, mc) = (self.vertex_material(a), self.vertex_material(b), self.vertex_material(c));
+
+      ((ma.young_modulus + mb.young_modulus + mc.young_modulus) / 3.0,
+       (ma.young_modulus * ma.weft_ratio + mb.young_modulus * mb.weft_ratio +
+        mc.young_modulus * mc.weft_ratio) / 3.0,
+       (ma.poisson_ratio + mb.poisson_ratio + mc.poisson_ratio) / 3.0)
+    }).collect()
+  }
+}
+
+// Triangles adjacent to each edge of the mesh, indexed by `edge_key`.
+fn edge_faces(mesh: &Mesh) -> HashMap<(u32, u32), ~[uint]>
+{
+  let mut edges = HashMap::new();
+
+  for (f, &(a, b, c)) in mesh.ibuff.iter().enumerate()
+  {
+    for &(e1, e2) in [(a, b), (b, c), (c, a)].iter()
+    { edges.find_or_insert(if e1 < e2 { (e1, e2) } else { (e2, e1) }, ~[]).push(f) }
+  }
+
+  edges
+}
+
+fn edge_key(i1: i32, i2: i32) -> (u32, u32)
+{ if i1 < i2 { (i1 as u32, i2 as u32) } else { (i2 as u32, i1 as u32) } }
@@ -0,0 +1,185 @@
+use std::num::{Zero, One};
+use nalgebra::traits::division_ring::DivisionRing;
+use nalgebra::traits::norm::Norm;
+use nalgebra::traits::dot::Dot;
+use nalgebra::traits::cross::Cross;
+use nalgebra::traits::vector_space::VectorSpace;
+use soft_body::{SoftBody, PointMass};
+
+/// Constitutive model of the membrane.
+#[deriving(Eq, Clone)]
+pub enum MembraneModel
+{
+  /// Linear elasticity in the rotated frame of each triangle: cheap, and accurate for large
+  /// rotations with small strains.
+  CoRotational,
+  /// St. Venant-Kirchhoff: linear stress in the Green strain.
+  StVK
+}
+
+/// Linear finite element on a triangle, under plane stress.
+///
+/// The material frame of the element has its first axis along the warp direction and its second
+/// one along the weft direction.
+pub struct MembraneElement<N>
+{
+  ids:    (uint, uint, uint),
+  /// Inverse of the matrix of the rest edges `(b - a, c - a)` in the material frame, row-major.
+  dm_inv: (N, N, N, N),
+  area:   N,
+  /// Orthotropic stiffness: `S = (c11 E11 + c12 E22, c12 E11 + c22 E22, c33 2 E12)`.
+  c11:    N,
+  c22:    N,
+  c12:    N,
+  c33:    N,
+  model:  MembraneModel
+}
+
+impl<N: DivisionRing + NumCast + Signed + Orderable + Bounded + Round + Trigonometric + Eq + Ord + Clone,
+     V: VectorSpace<N> + Norm<N> + Dot<N> + Cross<V> + Clone>
+    SoftBody<N, V>
+{
+  /// Adds a membrane element on each triangle, with their current shape as rest shape.
+  ///
+  /// `params` are the Young's moduli along the warp and the weft directions and the Poisson ratio
+  /// of each triangle. The moduli are per unit area, i.e. multiplied by the thickness of the
+  /// cloth. The warp direction of each triangle is `warp` projected on its plane, or its first
+  /// edge if they are orthogonal.
+  ///
+  /// Membrane forces are applied at integration: stiff materials need substeps.
+  pub fn add_membrane(&mut self,
+                      triangles: &[(uint, uint, uint)],
+                      params:    &[(N, N, N)],
+                      warp:      &V,
+                      model:     MembraneModel)
+  {
+    assert!(triangles.len() == params.len(), "Each triangle must have membrane parameters.");
+
+    let eps = NumCast::from::<N, float>(1.0e-6);
+    let _1  = One::one::<N>();
+    let two = NumCast::from::<N, float>(2.0);
+
+    for (&(a, b, c), &(ref e_warp, ref e_weft, ref nu)) in triangles.iter().zip(params.iter())
+    {
+      let pa = self.points[a].position.clone();
+      let e1 = self.points[b].position - pa;
+      let e2 = self.points[c].position - pa;
+
+      let mut normal = e1.cross(&e2);
+
+      if normal.normalize() < eps
+      { loop }
+
+      let mut u = *warp - normal.scalar_mul(&normal.dot(warp));
+
+      if u.normalize() < eps
+      { u = e1.normalized() }
+
+      let v = normal.cross(&u);
+
+      let (m00, m01, m10, m11) = (u.dot(&e1), u.dot(&e2), v.dot(&e1), v.dot(&e2));
+      let det = m00 * m11 - m01 * m10;
+
+      // plane stress orthotropic stiffness, with the reciprocal Poisson ratio `nu * weft / warp`
+      let nu21  = *nu * *e_weft / *e_warp;
+      let denom = _1 - *nu * nu21;
+
+      self.membrane.push(MembraneElement {
+        ids:    (a, b, c),
+        dm_inv: (m11 / det, -m01 / det, -m10 / det, m00 / det),
+        area:   det.abs() / two,
+        c11:    *e_warp / denom,
+        c22:    *e_weft / denom,
+        c12:    *nu * *e_weft / denom,
+        // Huber's approximation of the shear modulus
+        c33:    (*e_warp * *e_weft).sqrt() / (two * (_1 + *nu)),
+        model:  model
+      })
+    }
+  }
+
+  pub fn remove_membrane(&mut self)
+  { self.membrane = ~[] }
+}
+
+impl<N: DivisionRing + NumCast + Trigonometric + Ord + Clone> MembraneElement<N>
+{
+  /// Adds the elastic forces of the element to `forces`.
+  pub fn accumulate_forces<V: VectorSpace<N> + Norm<N> + Dot<N> + Clone>(&self,
+                                                                         points: &[PointMass<N, V>],
+                                                                         forces: &mut [V])
+  {
+    let _1   = One::one::<N>();
+    let half = NumCast::from::<N, float>(0.5);
+
+    let (a, b, c)            = self.ids;
+    let (i00, i01, i10, i11) = self.dm_inv.clone();
+    let d1                   = points[b].position - points[a].position;
+    let d2                   = points[c].position - points[a].position;
+
+    // deformation gradient F = Ds Dm^-1, as columns
+    let f1 = d1.scalar_mul(&i00) + d2.scalar_mul(&i10);
+    let f2 = d1.scalar_mul(&i01) + d2.scalar_mul(&i11);
+
+    // first Piola-Kirchhoff stress P, as columns
+    let (p1, p2) = match self.model
+    {
+      StVK =>
+      {
+        let e11 = (f1.dot(&f1) - _1) * half;
+        let e22 = (f2.dot(&f2) - _1) * half;
+        let e12 = f1.dot(&f2) * half;
+
+        let s11 = self.c11 * e11 + self.c12 * e22;
+        let s22 = self.c12 * e11 + self.c22 * e22;
+        let s12 = self.c33 * (e12 + e12);
+
+        (f1.scalar_mul(&s11) + f2.scalar_mul(&s12), f1.scalar_mul(&s12) + f2.scalar_mul(&s22))
+      },
+      CoRotational =>
+      {
+        // orthonormal frame of the deformed triangle
+        let mut q1 = f1.clone();
+        let     l1 = q1.normalize();
+        let mut q2 = f2 - q1.scalar_mul(&q1.dot(&f2));
+        let     l2 = q2.normalize();
+
+        if l1.is_zero() || l2.is_zero()
+        { return }
+
+        // F in this frame is upper triangular: polar decomposition of a 2x2 matrix
+        let (g00, g01, g11) = (l1, q1.dot(&f2), l2);
+        let theta           = (-g01).atan2(&(g00 + g11));
+        let (s, co)         = (theta.sin(), theta.cos());
+
+        // U = R^t F
+        let u00 = co * g00;
+        let u01 = co * g01 + s * g11;
+        let u10 = -s * g00;
+        let u11 = -s * g01 + co * g11;
+
+        let e11 = u00 - _1;
+        let e22 = u11 - _1;
+        let e12 = (u01 + u10) * half;
+
+        let s11 = self.c11 * e11 + self.c12 * e22;
+        let s22 = self.c12 * e11 + self.c22 * e22;
+        let s12 = self.c33 * (e12 + e12);
+
+        // P = Q R S
+        let r1 = q1.scalar_mul(&co) + q2.scalar_mul(&s);
+        let r2 = q2.scalar_mul(&co) - q1.scalar_mul(&s);
+
+        (r1.scalar_mul(&s11) + r2.scalar_mul(&s12), r1.scalar_mul(&s12) + r2.scalar_mul(&s22))
+      }
+    };
+
+    // forces: -area P Dm^-t
+    let fb = (p1.scalar_mul(&i00) + p2.scalar_mul(&i01)).scalar_mul(&(-self.area));
+    let fc = (p1.scalar_mul(&i10) + p2.scalar_mul(&i11)).scalar_mul(&(-self.area));
+
+    forces[a] = forces[a] - fb - fc;
+    forces[b] = forces[b] + fb;
+    forces[c] = forces[c] + fc;
+  }
+}
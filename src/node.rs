@@ -1,6 +1,5 @@
 extern mod extra;
 extern mod nalgebra;
-extern mod kiss3d;
 
 use std::vec;
 use extra::sort::Sort;
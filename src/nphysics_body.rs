@@ -0,0 +1,116 @@
+use std::num::Zero;
+use nalgebra::vec::Vec3;
+use nalgebra::traits::dot::Dot;
+use nalgebra::traits::norm::Norm;
+use nalgebra::traits::rlmul::RMul;
+use nalgebra::traits::rotation::Rotate;
+use nalgebra::traits::transformation::Transform;
+use nphysics::aliases::dim3::RigidBody3d;
+use ncollide::geom::default_geom::{Plane, Ball, Box};
+use rigid_coupling::RigidBodyProxy;
+
+/// Rigid body of an nphysics world, coupled with soft bodies. The nphysics world keeps
+/// integrating it; the soft bodies only change its velocities.
+pub struct NPhysicsBody
+{
+  body: @mut RigidBody3d<f64>
+}
+
+impl NPhysicsBody
+{
+  pub fn new(body: @mut RigidBody3d<f64>) -> NPhysicsBody
+  { NPhysicsBody { body: body } }
+}
+
+impl RigidBodyProxy<f64, Vec3<f64>> for NPhysicsBody
+{
+  fn center_of_mass(&self) -> Vec3<f64>
+  { self.body.center_of_mass().clone() }
+
+  fn lin_vel(&self) -> Vec3<f64>
+  { self.body.lin_vel() }
+
+  fn ang_vel(&self) -> Vec3<f64>
+  { self.body.ang_vel() }
+
+  fn inv_mass(&self) -> f64
+  { if self.body.can_move() { self.body.inv_mass() } else { 0.0 } }
+
+  fn friction(&self) -> f64
+  { self.body.friction() }
+
+  fn inv_inertia_mul(&self, v: &Vec3<f64>) -> Vec3<f64>
+  { if self.body.can_move() { self.body.inv_inertia().rmul(v) } else { Zero::zero() } }
+
+  fn apply_velocity_change(&mut self, dlv: &Vec3<f64>, dav: &Vec3<f64>)
+  {
+    if self.body.can_move()
+    {
+      let lv = self.body.lin_vel() + *dlv;
+      let av = self.body.ang_vel() + *dav;
+
+      self.body.set_lin_vel(lv);
+      self.body.set_ang_vel(av);
+    }
+  }
+
+  fn to_world(&self, local: &Vec3<f64>) -> Vec3<f64>
+  { self.body.transform_ref().transform_vec(local) }
+
+  fn to_local(&self, world: &Vec3<f64>) -> Vec3<f64>
+  { self.body.transform_ref().inv_transform(world) }
+
+  // Computed in the local frame of the body, for the planes, balls and boxes of ncollide. Other
+  // geometries do not collide with the points.
+  fn contact(&self, point: &Vec3<f64>, margin: &f64) -> Option<(Vec3<f64>, f64)>
+  {
+    let local = self.to_local(point);
+
+    let res = match *self.body.geom()
+    {
+      Plane(ref p) => Some((p.normal(), p.normal().dot(&local))),
+      Ball(ref b)  =>
+      {
+        let mut n    = local.clone();
+        let     dist = n.normalize();
+
+        if dist == 0.0
+        { Some((Vec3::new(0.0, 0.0, 1.0), -b.radius())) }
+        else
+        { Some((n, dist - b.radius())) }
+      },
+      Box(ref b)   => Some(box_contact(&b.half_extents(), &local)),
+      _            => None
+    };
+
+    match res
+    {
+      Some((n, dist)) if dist < *margin => Some((self.body.transform_ref().rotate(&n), dist)),
+      _                                 => None
+    }
+  }
+}
+
+// Outward normal and signed distance of the surface of a box centered at the origin, closest to
+// `p`.
+fn box_contact(half_extents: &Vec3<f64>, p: &Vec3<f64>) -> (Vec3<f64>, f64)
+{
+  let d = Vec3::new(p.x.abs() - half_extents.x, p.y.abs() - half_extents.y, p.z.abs() - half_extents.z);
+
+  if d.x > 0.0 || d.y > 0.0 || d.z > 0.0
+  {
+    // outside: toward the closest point of the box
+    let mut n    = Vec3::new(p.x.signum() * d.x.max(&0.0),
+                             p.y.signum() * d.y.max(&0.0),
+                             p.z.signum() * d.z.max(&0.0));
+    let     dist = n.normalize();
+
+    (n, dist)
+  }
+  else if d.x >= d.y && d.x >= d.z
+  { (Vec3::new(p.x.signum(), 0.0, 0.0), d.x) }
+  else if d.y >= d.z
+  { (Vec3::new(0.0, p.y.signum(), 0.0), d.y) }
+  else
+  { (Vec3::new(0.0, 0.0, p.z.signum()), d.z) }
+}
@@ -0,0 +1,11 @@
+use kiss3d::object::{VerticesNormalsTriangles, Object};
+use graph::Mesh;
+
+pub fn object2mesh(obj: @mut Object) -> Mesh
+{
+  match obj.geometry()
+  {
+    &VerticesNormalsTriangles(ref vs, _, ref ts) => Mesh::new(vs.clone(), ts.clone()),
+    _ => fail!("Unable to build the soft body without geometric informations.")
+  }
+}
@@ -0,0 +1,22 @@
+/// A vertex held by the simulation: its inverse mass is set to zero while pinned, the original one
+/// being kept here to be restored when the vertex is released.
+///
+/// If a target is given, the vertex is kinematic and follows the trajectory `target(t)`.
+pub struct Pin<N, V>
+{
+  id:      uint,
+  invmass: N,
+  target:  Option<@fn(N) -> V>
+}
+
+impl<N, V> Pin<N, V>
+{
+  pub fn new(id: uint, invmass: N, target: Option<@fn(N) -> V>) -> Pin<N, V>
+  {
+    Pin {
+      id:      id,
+      invmass: invmass,
+      target:  target
+    }
+  }
+}
@@ -0,0 +1,50 @@
+use std::num::{Zero, One};
+use nalgebra::traits::division_ring::DivisionRing;
+use nalgebra::traits::norm::Norm;
+use nalgebra::traits::dot::Dot;
+use nalgebra::traits::cross::Cross;
+use nalgebra::traits::vector_space::VectorSpace;
+use soft_body::SoftBody;
+
+impl<N: DivisionRing + NumCast + Signed + Orderable + Bounded + Round + Trigonometric + Eq + Ord + Clone,
+     V: VectorSpace<N> + Norm<N> + Dot<N> + Cross<V> + Clone>
+    SoftBody<N, V>
+{
+  /// Makes every constraint plastic: when its strain exceeds `yield_strain`, its rest length
+  /// creeps toward its current length at the rate `creep` (fraction of the excess strain absorbed
+  /// per second). The rest length never deviates from its initial value by more than
+  /// `max_plastic_strain` times this value.
+  pub fn set_plasticity(&mut self, yield_strain: N, creep: N, max_plastic_strain: N)
+  {
+    for c in self.constraints.mut_iter()
+    {
+      c.yield_strain       = yield_strain.clone();
+      c.creep              = creep.clone();
+      c.max_plastic_strain = max_plastic_strain.clone();
+    }
+  }
+
+  /// Updates the rest lengths of the constraints strained beyond their yield threshold.
+  pub fn update_plasticity(&mut self, dt: &N)
+  {
+    for c in self.constraints.mut_iter()
+    {
+      if c.creep.is_zero() || c.rest_length.is_zero()
+      { loop }
+
+      let length = (self.points[c.rb1].position - self.points[c.rb2].position).norm();
+      let strain = (length - c.rest_length) / c.rest_length;
+
+      if strain.abs() <= c.yield_strain
+      { loop }
+
+      let excess = strain - c.yield_strain * strain.signum();
+      let rate   = (c.creep * *dt).min(&One::one());
+      let rest   = c.rest_length + c.rest_length * excess * rate;
+      let lo     = c.initial_rest_length * (One::one::<N>() - c.max_plastic_strain).max(&Zero::zero());
+      let hi     = c.initial_rest_length * (One::one::<N>() + c.max_plastic_strain);
+
+      c.rest_length = rest.max(&lo).min(&hi);
+    }
+  }
+}
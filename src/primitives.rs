@@ -0,0 +1,40 @@
+use nalgebra::vec::Vec3;
+use graph::Mesh;
+
+// Same layout as kiss3d's `add_quad`, so that vertex indices (and thus pinned vertices) match
+// between the windowed demos and the headless runner.
+pub fn quad(w: f32, h: f32, wsubdivs: uint, hsubdivs: uint) -> Mesh
+{
+  assert!(wsubdivs > 0 && hsubdivs > 0, "The number of subdivisions cannot be zero");
+
+  let wstep = w / (wsubdivs as f32);
+  let hstep = h / (hsubdivs as f32);
+  let cw    = w / 2.0;
+  let ch    = h / 2.0;
+
+  let mut vertices  = ~[];
+  let mut triangles = ~[];
+
+  for i in range(0u, hsubdivs + 1)
+  {
+    for j in range(0u, wsubdivs + 1)
+    { vertices.push(Vec3::new(j as f32 * wstep - cw, i as f32 * hstep - ch, 0.0f32)) }
+  }
+
+  fn dl_triangle(i: u32, j: u32, ws: u32) -> (u32, u32, u32)
+  { ((i + 1) * ws + j, i * ws + j, (i + 1) * ws + j + 1) }
+
+  fn ur_triangle(i: u32, j: u32, ws: u32) -> (u32, u32, u32)
+  { (i * ws + j, i * ws + (j + 1), (i + 1) * ws + j + 1) }
+
+  for i in range(0u, hsubdivs)
+  {
+    for j in range(0u, wsubdivs)
+    {
+      triangles.push(dl_triangle(i as u32, j as u32, (wsubdivs + 1) as u32));
+      triangles.push(ur_triangle(i as u32, j as u32, (wsubdivs + 1) as u32));
+    }
+  }
+
+  Mesh::new(vertices, triangles)
+}
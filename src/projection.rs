@@ -0,0 +1,106 @@
+use std::num::Zero;
+use nalgebra::traits::division_ring::DivisionRing;
+use nalgebra::traits::dot::Dot;
+use nalgebra::traits::vector_space::VectorSpace;
+use soft_body::PointMass;
+
+// Resolution of scalar constraints `C(x_ids) = c` involving any number of points, given their
+// gradients `grads` with respect to each point.
+
+/// Scalar constraint involving any number of points, with its value and its gradients at the
+/// current positions. The positions do not move during a velocity-level solve: they are computed
+/// once per solve, then the constraint is projected at each Gauss-Seidel iteration (see
+/// `sweep_velocities`).
+pub struct VelocityProjection<N, V>
+{
+  ids:       ~[uint],
+  grads:     ~[V],
+  value:     N,
+  stiffness: N
+}
+
+/// One `project_velocities` of each constraint of `projections`.
+pub fn sweep_velocities<N: DivisionRing + Clone, V: VectorSpace<N> + Dot<N> + Clone>(
+                        points:      &mut [PointMass<N, V>],
+                        projections: &[VelocityProjection<N, V>],
+                        dt:          &N,
+                        fext_dt:     &V)
+{
+  for p in projections.iter()
+  { project_velocities(points, p.ids, p.grads, &p.value, &p.stiffness, dt, fext_dt) }
+}
+
+/// Velocity-level resolution: the rate of change of the constraint is driven towards
+/// `-dt * stiffness * c`, like the distance constraints given to the PGS solver. `fext_dt` is the
+/// velocity change due to external forces at the next integration.
+pub fn project_velocities<N: DivisionRing + Clone, V: VectorSpace<N> + Dot<N> + Clone>(
+                          points:    &mut [PointMass<N, V>],
+                          ids:       &[uint],
+                          grads:     &[V],
+                          c:         &N,
+                          stiffness: &N,
+                          dt:        &N,
+                          fext_dt:   &V)
+{
+  let mut objective = *dt * *stiffness * *c;
+  let mut denom     = Zero::zero::<N>();
+
+  for (i, g) in ids.iter().zip(grads.iter())
+  {
+    let p = &points[*i];
+
+    if p.invmass.is_zero()
+    { objective = objective + g.dot(&p.velocity) }
+    else
+    {
+      objective = objective + g.dot(&(p.velocity + *fext_dt));
+      denom     = denom + p.invmass * g.dot(g);
+    }
+  }
+
+  if denom.is_zero()
+  { return }
+
+  let lambda = objective / denom;
+
+  for (i, g) in ids.iter().zip(grads.iter())
+  {
+    let p = &mut points[*i];
+
+    if !p.invmass.is_zero()
+    { p.velocity = p.velocity - g.scalar_mul(&(p.invmass * lambda)) }
+  }
+}
+
+/// XPBD resolution: `alpha` is the compliance divided by the squared timestep and `lambda` the
+/// Lagrange multiplier accumulated during the current step.
+pub fn project_positions<N: DivisionRing + Clone, V: VectorSpace<N> + Dot<N> + Clone>(
+                         points: &mut [PointMass<N, V>],
+                         ids:    &[uint],
+                         grads:  &[V],
+                         c:      &N,
+                         alpha:  &N,
+                         lambda: &mut N)
+{
+  let mut denom = alpha.clone();
+  let mut wsum  = Zero::zero::<N>();
+
+  for (i, g) in ids.iter().zip(grads.iter())
+  { wsum = wsum + points[*i].invmass * g.dot(g) }
+
+  denom = denom + wsum;
+
+  if wsum.is_zero() || denom.is_zero()
+  { return }
+
+  let dlambda = (-*c - *alpha * *lambda) / denom;
+
+  *lambda = *lambda + dlambda;
+
+  for (i, g) in ids.iter().zip(grads.iter())
+  {
+    let p = &mut points[*i];
+
+    p.position = p.position + g.scalar_mul(&(p.invmass * dlambda));
+  }
+}
@@ -0,0 +1,181 @@
+use std::util;
+use std::num::{Zero, One};
+use nalgebra::traits::division_ring::DivisionRing;
+use nalgebra::traits::norm::Norm;
+use nalgebra::traits::dot::Dot;
+use nalgebra::traits::cross::Cross;
+use nalgebra::traits::dim::Dim;
+use nalgebra::traits::indexable::Indexable;
+use nalgebra::traits::vector_space::VectorSpace;
+use soft_body::{SoftBody, PointMass, ConstraintsGeometry};
+use skyline::SkylineLDLT;
+
+/// Prefactored global matrix of the Projective Dynamics solver: `M / dt^2 + sum(w L^t L)` over
+/// the springs, restricted to the points which are not pinned.
+pub struct ProjectiveSystem<N>
+{
+  /// Timestep the matrix was built for.
+  dt:     N,
+  /// Row of each point in the system, `-1` for pinned points.
+  rows:   ~[int],
+  /// Point of each row.
+  dofs:   ~[uint],
+  factor: SkylineLDLT<N>
+}
+
+impl<N: DivisionRing + Clone> ProjectiveSystem<N>
+{
+  pub fn new<V>(points: &[PointMass<N, V>], constraints: &[ConstraintsGeometry<N>], dt: N)
+                -> ProjectiveSystem<N>
+  {
+    let mut rows = ~[];
+    let mut dofs = ~[];
+
+    for (i, p) in points.iter().enumerate()
+    {
+      if p.invmass.is_zero()
+      { rows.push(-1) }
+      else
+      {
+        rows.push(dofs.len() as int);
+        dofs.push(i);
+      }
+    }
+
+    let dt2         = dt * dt;
+    let mut entries = ~[];
+
+    for (row, i) in dofs.iter().enumerate()
+    { entries.push((row, row, One::one::<N>() / (points[*i].invmass * dt2))) }
+
+    for c in constraints.iter()
+    {
+      if c.stiffness.is_zero()
+      { loop }
+
+      let (r1, r2) = (rows[c.rb1], rows[c.rb2]);
+
+      if r1 >= 0
+      { entries.push((r1 as uint, r1 as uint, c.stiffness.clone())) }
+
+      if r2 >= 0
+      { entries.push((r2 as uint, r2 as uint, c.stiffness.clone())) }
+
+      if r1 >= 0 && r2 >= 0
+      {
+        let (r, c2) = if r1 > r2 { (r1, r2) } else { (r2, r1) };
+
+        entries.push((r as uint, c2 as uint, -c.stiffness));
+      }
+    }
+
+    ProjectiveSystem {
+      dt:     dt,
+      rows:   rows,
+      factor: SkylineLDLT::new(dofs.len(), entries),
+      dofs:   dofs
+    }
+  }
+}
+
+impl<V: VectorSpace<N> + Dot<N> + Norm<N> + Cross<V> + Indexable<uint, N> + Dim + Clone + ToStr,
+     N:  DivisionRing + Orderable + NumCast + Signed + Bounded + Round + Trigonometric + Ord + ToStr + Eq + Clone>
+     SoftBody<N, V>
+{
+  /// Projective Dynamics: alternates the projection of each spring on its rest length and a
+  /// global solve with the factorized matrix, starting from the positions predicted by
+  /// `integrate`. The spring stiffness is used as the weight of the springs.
+  ///
+  /// The matrix is factorized on the first call, and rebuilt when the pins or the timestep
+  /// change. Contacts, bending and volume constraints are projected after each global solve.
+  /// Rigid bodies are not supported.
+  pub fn solve_projective(&mut self, dt: N)
+  {
+    assert!(self.rigid_bodies.is_empty(), "Rigid bodies require a velocity-level solver.");
+
+    let dt2 = dt * dt;
+
+    let sys = match util::replace(&mut self.projective, None)
+    {
+      Some(sys) => if sys.dt == dt { sys } else { ProjectiveSystem::new(self.points, self.constraints, dt.clone()) },
+      None      => ProjectiveSystem::new(self.points, self.constraints, dt.clone())
+    };
+
+    // inertial term of the right hand side
+    let inertia: ~[V] = sys.dofs.iter().transform(|i| {
+      let p = &self.points[*i];
+
+      p.position.scalar_div(&(p.invmass * dt2))
+    }).collect();
+
+    for b in self.bendings.mut_iter()
+    { b.impulse = Zero::zero() }
+
+    for v in self.volumes.mut_iter()
+    { v.impulse = Zero::zero() }
+
+    for i in self.inflation.mut_iter()
+    { i.impulse = Zero::zero() }
+
+    for _ in range(0u, self.iterations)
+    {
+      let mut rhs = inertia.clone();
+
+      // local step
+      for c in self.constraints.iter()
+      {
+        if c.stiffness.is_zero()
+        { loop }
+
+        let p1 = self.points[c.rb1].position.clone();
+        let p2 = self.points[c.rb2].position.clone();
+        let d  = p1 - p2;
+        let l  = d.norm();
+
+        let projection = if l.is_zero() { Zero::zero() } else { d.scalar_mul(&(c.rest_length / l)) };
+        let weighted   = projection.scalar_mul(&c.stiffness);
+
+        // pinned endpoints are moved to the right hand side
+        let (r1, r2) = (sys.rows[c.rb1], sys.rows[c.rb2]);
+
+        if r1 >= 0
+        {
+          let r = r1 as uint;
+
+          rhs[r] = rhs[r] + weighted;
+
+          if r2 < 0
+          { rhs[r] = rhs[r] + p2.scalar_mul(&c.stiffness) }
+        }
+
+        if r2 >= 0
+        {
+          let r = r2 as uint;
+
+          rhs[r] = rhs[r] - weighted;
+
+          if r1 < 0
+          { rhs[r] = rhs[r] + p1.scalar_mul(&c.stiffness) }
+        }
+      }
+
+      // global step
+      let positions = sys.factor.solve(rhs);
+
+      for (i, pos) in sys.dofs.iter().zip(positions.iter())
+      { self.points[*i].position = pos.clone() }
+
+      self.solve_bending_positions(&dt2);
+      self.solve_volume_positions(&dt2);
+      self.project_contacts(&dt);
+    }
+
+    for p in self.points.mut_iter()
+    {
+      if !p.invmass.is_zero()
+      { p.velocity = (p.position - p.last_position).scalar_div(&dt) }
+    }
+
+    self.projective = Some(sys);
+  }
+}
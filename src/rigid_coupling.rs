@@ -0,0 +1,222 @@
+use std::num::{Zero, One};
+use nalgebra::traits::division_ring::DivisionRing;
+use nalgebra::traits::norm::Norm;
+use nalgebra::traits::dot::Dot;
+use nalgebra::traits::cross::Cross;
+use nalgebra::traits::dim::Dim;
+use nalgebra::traits::indexable::Indexable;
+use nalgebra::traits::vector_space::VectorSpace;
+use nphysics::resolution::constraint::velocity_constraint::VelocityConstraint;
+use soft_body::{SoftBody, PointMass, XPBDSolver, ProjectiveDynamicsSolver};
+
+/// Rigid body seen by the soft bodies: wraps the rigid bodies of an nphysics world, which keeps
+/// integrating them. Velocities and positions are in world space; angular quantities are
+/// vectors of the same dimension as the positions.
+pub trait RigidBodyProxy<N, V>
+{
+  fn center_of_mass(&self) -> V;
+  fn lin_vel(&self) -> V;
+  fn ang_vel(&self) -> V;
+  /// Zero for static and kinematic bodies.
+  fn inv_mass(&self) -> N;
+  /// Friction coefficient of the contacts between the points and the body.
+  fn friction(&self) -> N;
+  /// Product of the world-space inverse inertia tensor with `v`.
+  fn inv_inertia_mul(&self, v: &V) -> V;
+  /// Applies the velocity changes due to the soft bodies.
+  fn apply_velocity_change(&mut self, dlv: &V, dav: &V);
+  /// Transforms a point from the local frame of the body to world space.
+  fn to_world(&self, local: &V) -> V;
+  /// Transforms a point from world space to the local frame of the body.
+  fn to_local(&self, world: &V) -> V;
+  /// Outward normal and signed distance of the surface of the body closest to `point`, if it is
+  /// closer than `margin`.
+  fn contact(&self, point: &V, margin: &N) -> Option<(V, N)>;
+}
+
+/// Point of a soft body held at a fixed location of a rigid body.
+pub struct RigidAttachment<V>
+{
+  point:  uint,
+  body:   uint,
+  /// Location of the attachment in the local frame of the body.
+  anchor: V
+}
+
+impl<V: VectorSpace<N> + Dot<N> + Norm<N> + Cross<V> + Indexable<uint, N> + Dim + Clone + ToStr,
+     N:  DivisionRing + Orderable + NumCast + Signed + Bounded + Round + Trigonometric + Ord + ToStr + Eq + Clone>
+     SoftBody<N, V>
+{
+  /// Makes a rigid body collide with the points, and enables attachments to it. Returns its
+  /// index.
+  ///
+  /// Rigid bodies are coupled through the velocity-level solver: the constraint impulses are
+  /// applied to both the points and the rigid bodies. The body must not use the position-based
+  /// solvers, which have no coupling.
+  pub fn add_rigid_body(&mut self, body: @mut RigidBodyProxy<N, V>) -> uint
+  {
+    assert!(self.solver != XPBDSolver && self.solver != ProjectiveDynamicsSolver,
+            "Rigid bodies require a velocity-level solver.");
+
+    self.rigid_bodies.push(body);
+    self.rigid_bodies.len() - 1
+  }
+
+  /// Removes a rigid body and the attachments to it. The indices of the next bodies are
+  /// decremented.
+  pub fn remove_rigid_body(&mut self, body: uint) -> @mut RigidBodyProxy<N, V>
+  {
+    self.rigid_attachments.retain(|a| a.body != body);
+
+    for a in self.rigid_attachments.mut_iter()
+    {
+      if a.body > body
+      { a.body = a.body - 1 }
+    }
+
+    self.rigid_bodies.remove(body)
+  }
+
+  /// Attaches the point `i` to the rigid body `body`, at its current location.
+  pub fn attach_to_rigid_body(&mut self, i: uint, body: uint)
+  {
+    let anchor = self.rigid_bodies[body].to_local(&self.points[i].position);
+
+    self.rigid_attachments.push(RigidAttachment { point: i, body: body, anchor: anchor });
+  }
+
+  pub fn detach_from_rigid_bodies(&mut self, i: uint)
+  { self.rigid_attachments.retain(|a| a.point != i) }
+
+  /// Contacts between the points and the rigid bodies, and the attachments to the rigid bodies.
+  /// The rigid body `k` has the index `points.len() + k` in the solver. Pinned points push the
+  /// rigid bodies as if their mass was infinite.
+  pub fn collect_rigid_constraints(&self,
+                                   dt:       N,
+                                   out:      &mut ~[VelocityConstraint<V, V, N>],
+                                   friction: &mut ~[VelocityConstraint<V, V, N>])
+  {
+    // fraction of the position error corrected at each step
+    let erp: N  = NumCast::from::<N, float>(0.4);
+    let npoints = self.points.len();
+    let fext_dt = self.ext_forces.scalar_mul(&dt);
+
+    for (k, body) in self.rigid_bodies.iter().enumerate()
+    {
+      let id = npoints + k;
+
+      let fixed = body.inv_mass().is_zero();
+
+      for (i, p) in self.points.iter().enumerate()
+      {
+        if p.invmass.is_zero() && fixed
+        { loop }
+
+        match body.contact(&p.position, &self.margin)
+        {
+          Some((n, dist)) =>
+          {
+            let arm  = p.position - n.scalar_mul(&dist) - body.center_of_mass();
+            let vb   = body.lin_vel() + body.ang_vel().cross(&arm);
+            let vp   = if p.invmass.is_zero() { p.velocity.clone() } else { p.velocity + fext_dt };
+            let vrel = vp - vb;
+            let vn   = vrel.dot(&n);
+            let bias = if dist < Zero::zero() { -dist * erp / dt } else { -dist / dt };
+
+            // a positive impulse pushes the point along `n` and the body along `-n`
+            out.push(rigid_constraint(self.points, i, *body, id, &arm, -n, bias - vn,
+                                      Zero::zero(), Bounded::max_value()));
+
+            let mut tangent = vrel - n.scalar_mul(&vn);
+            let     vt      = tangent.normalize();
+
+            if vt > Zero::zero()
+            {
+              let mut f = rigid_constraint(self.points, i, *body, id, &arm, tangent, vt,
+                                           Zero::zero(), Zero::zero());
+
+              f.friction_limit_id = out.len() - 1;
+              f.friction_coeff    = body.friction();
+
+              friction.push(f);
+            }
+          },
+          None => { }
+        }
+      }
+    }
+
+    for a in self.rigid_attachments.iter()
+    {
+      let body = self.rigid_bodies[a.body];
+      let p    = &self.points[a.point];
+
+      if p.invmass.is_zero() && body.inv_mass().is_zero()
+      { loop }
+
+      let anchor = body.to_world(&a.anchor);
+      let arm    = anchor - body.center_of_mass();
+      let vb     = body.lin_vel() + body.ang_vel().cross(&arm);
+      let vp     = if p.invmass.is_zero() { p.velocity.clone() } else { p.velocity + fext_dt };
+      let error  = p.position - anchor;
+
+      // one bilateral constraint per axis
+      for d in range(0u, Dim::dim::<V>())
+      {
+        let mut axis = Zero::zero::<V>();
+
+        axis.set(d, One::one());
+
+        let objective = axis.dot(&(vp - vb)) + axis.dot(&error) * erp / dt;
+
+        out.push(rigid_constraint(self.points, a.point, body, npoints + a.body, &arm, axis, objective,
+                                  -Bounded::max_value::<N>(), Bounded::max_value()));
+      }
+    }
+  }
+}
+
+// Constraint between the point `i` and the rigid body `body`, of index `id` in the solver, at
+// `arm` from its center of mass. Same conventions as `velocity_constraint`: a positive impulse
+// pushes the point along `-normal` and the body along `normal`.
+fn rigid_constraint<N: DivisionRing + Clone, V: VectorSpace<N> + Dot<N> + Cross<V> + Clone>(
+                    points:    &[PointMass<N, V>],
+                    i:         uint,
+                    body:      @mut RigidBodyProxy<N, V>,
+                    id:        uint,
+                    arm:       &V,
+                    normal:    V,
+                    objective: N,
+                    lobound:   N,
+                    hibound:   N) -> VelocityConstraint<V, V, N>
+{
+  let m1        = points[i].invmass.clone();
+  let m2        = body.inv_mass();
+  let rot_axis2 = arm.cross(&normal);
+  let weighted2 = body.inv_inertia_mul(&rot_axis2);
+
+  VelocityConstraint {
+    weighted_normal1:   normal.scalar_mul(&m1),
+    weighted_normal2:   normal.scalar_mul(&m2),
+
+    rot_axis1:          Zero::zero(),
+    weighted_rot_axis1: Zero::zero(),
+
+    inv_projected_mass: One::one::<N>() / (m1 + m2 + rot_axis2.dot(&weighted2)),
+
+    rot_axis2:          rot_axis2,
+    weighted_rot_axis2: weighted2,
+
+    impulse:            Zero::zero(),
+    unit_impulse:       Zero::zero(),
+    lobound:            lobound,
+    hibound:            hibound,
+    objective:          objective,
+    id1:                if m1.is_zero() { -1 } else { i as int },
+    id2:                id as int,
+
+    normal:             normal,
+    friction_limit_id:  0,
+    friction_coeff:     Zero::zero(),
+  }
+}
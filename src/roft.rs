@@ -3,8 +3,18 @@ use extra::time;
 use nalgebra::vec::Vec3;
 use kiss3d::window;
 use kiss3d::camera;
+use ncollide::geom::ball;
+use ncollide::geom::default_geom::DefaultGeom;
+use nphysics::aliases::dim3::{BodyWorld3d, RigidBody3d};
+use nphysics::object::body::RigidBody;
+use nphysics::object::rigid_body::Dynamic;
 use soft_body::SoftBody;
+use stepper::Stepper;
+use world::World;
 use builder;
+use material::{Material, MaterialMap};
+use object2mesh::object2mesh;
+use nphysics_body::NPhysicsBody;
 
 #[main]
 fn main()
@@ -14,28 +24,61 @@ fn main()
     let hsub = 75;
     let quad = w.add_quad(100.0, 100.0, hsub, 75).set_color(random(), random(), random());
 
+    let mesh      = object2mesh(quad);
+    let materials = MaterialMap::new(&mesh, Material::default());
+
     let (vertices, ids1, ids2, _, _, _, _, invmasses, stiffness) =
-      builder::soft_body_parameters(quad, hsub, false);
+      builder::soft_body_parameters(mesh, &materials, true, false);
+    let damping = materials.constraint_damping(ids1, ids2, stiffness);
+
     let soft_body = @mut SoftBody::from_mesh(vertices, ids1, ids2, invmasses, stiffness);
 
-    let timestep  = 0.016;
+    soft_body.set_damping(damping, materials.point_damping());
+
+    // hold the two upper corners
+    let nvertices = soft_body.points.len();
+    soft_body.pin(nvertices - 1);
+    soft_body.pin(nvertices - hsub - 1);
+
+    let gravity = Vec3::new(0.0f64, 0.0, -9.81);
+    let world   = @mut World::new(gravity.clone(), Stepper::new(0.016, 1));
+
+    world.add_body(soft_body);
+
+    // a ball dropped on the cloth, integrated by nphysics and pushed back by the cloth
+    let radius  = 10.0f64;
+    let physics = @mut BodyWorld3d::new();
+    let ball    = @mut RigidBody3d::new(DefaultGeom::new_ball(ball::Ball::new(radius)), 1.0f64, Dynamic, 0.3, 0.4);
+
+    physics.set_gravity(gravity);
+    ball.translate_by(&Vec3::new(50.0, 50.0, 40.0));
+    physics.add_body(@mut RigidBody(ball));
+    soft_body.add_rigid_body(@mut NPhysicsBody::new(ball));
+
+    let sphere = w.add_sphere(radius as f32).set_color(random(), random(), random());
+
+    let last_frame = @mut time::precise_time_s();
 
     do w.set_loop_callback
     {
       let before = time::precise_time_s();
 
-      soft_body.integrate(&timestep, &Vec3::new(0.0f64, 0.0, -9.81));
+      let nsteps = world.step(before - *last_frame);
+      *last_frame = before;
+
+      // the ball moves with the velocities set by the cloth
+      for _ in range(0u, nsteps)
+      { physics.step(world.stepper.dt.clone()) }
 
-      soft_body.solve(timestep.clone());
+      sphere.set_transformation(ball.transform_ref().clone());
 
-      // FIXME: solve constraints
       do quad.modify_vertices |vs|
       {
-        for (v, p) in vs.mut_iter().zip(soft_body.points.iter())
+        for (v, p) in vs.mut_iter().zip(world.interpolated_positions(0).iter())
         {
-          *v = Vec3::new(p.position.x as f32,
-                         p.position.y as f32,
-                         p.position.z as f32);
+          *v = Vec3::new(p.x as f32,
+                         p.y as f32,
+                         p.z as f32);
         }
 
         true
@@ -8,6 +8,8 @@ use OpenCL::hl::*;
 use rs2cl::nalgebra2cl::CLVec3f64;
 use soft_body_gpu::SoftBodyGpu;
 use builder;
+use material::{Material, MaterialMap};
+use object2mesh::object2mesh;
 use kernels;
 
 #[main]
@@ -22,7 +24,9 @@ fn main()
 
     let src  = kernels::integration_kernel()      +
                kernels::init_constraints_kernel() +
-               kernels::lin_pgs_solver_kernel();
+               kernels::lin_pgs_solver_kernel()   +
+               kernels::bending_solver_kernel()   +
+               kernels::strain_limiting_kernel();
     let prog = ctx.create_program_from_source(src);
 
     prog.build(ctx.device);
@@ -30,6 +34,8 @@ fn main()
     let integrator  = prog.create_kernel("integrate");
     let initializer = prog.create_kernel("init_constraints");
     let solver      = prog.create_kernel("lin_pgs_solve");
+    let bender      = prog.create_kernel("solve_bending");
+    let limiter     = prog.create_kernel("limit_strain");
 
     /*
      * Initialize simulation parameters.
@@ -37,12 +43,31 @@ fn main()
     let sub  = 75;
     let quad = w.add_quad(100.0, 100.0, sub, sub).set_color(random(), random(), random());
 
+    let mesh      = object2mesh(quad);
+    let mut material = Material::default();
+
+    // the cloth stretches by at most 10%
+    material.strain_limit = 0.1;
+
+    let materials = MaterialMap::new(&mesh, material);
+
     let (vertices, ids1, ids2, colors, colors_sizes, batches, batch_sizes, invmasses, stiffness) =
-      builder::soft_body_parameters(quad, sub, true);
+      builder::soft_body_parameters(mesh.clone(), &materials, true, true);
+
+    let damping       = materials.constraint_damping(ids1, ids2, stiffness);
+    let strain_limits = materials.strain_limits(&mesh, ids1, ids2);
 
     let cl_mvs = vertices.consume_iter().transform(|v| CLVec3f64::new(v)).collect();
     let soft_body = @mut SoftBodyGpu::from_mesh(
-      cl_mvs, ids1, ids2, colors, colors_sizes, batches, batch_sizes, invmasses, stiffness, &solver, ctx);
+      cl_mvs, ids1, ids2, colors, colors_sizes, batches, batch_sizes, invmasses, stiffness, ctx);
+
+    soft_body.set_damping(damping, materials.point_damping());
+    soft_body.set_strain_limits(strain_limits);
+
+    // hold the two upper corners
+    let nvertices = soft_body.positions.len();
+    soft_body.pin(nvertices - 1);
+    soft_body.pin(nvertices - sub - 1);
 
     let timestep: f64 = 0.016;
 
@@ -70,7 +95,8 @@ fn main()
       let gravity = CLVec3f64::new(Vec3::new(0.0f64, 0.00, -9.81f64));
       soft_body.integrate_gpu(&timestep, &gravity, &integrator, ctx);
 
-      soft_body.solve_gpu(&timestep, &solver, &initializer, ctx);
+      soft_body.solve_gpu(&timestep, &solver, &initializer, &bender, ctx);
+      soft_body.finish_substep_gpu(&timestep, &limiter, ctx);
 
       do quad.modify_vertices |vs|
       {
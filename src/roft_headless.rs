@@ -0,0 +1,452 @@
+use std::io;
+use std::os;
+use extra::time;
+use extra::getopts::*;
+use nalgebra::vec::Vec3;
+use soft_body::{SoftBody, PGSSolver, XPBDSolver, ImplicitEulerSolver,
+                ProjectiveDynamicsSolver};
+use builder;
+use primitives;
+use loader;
+use stepper::Stepper;
+use material::{Material, MaterialMap};
+use export::{ObjSequence, CacheWriter, Cache};
+use collision::{Collider, Plane, Sphere};
+use aerodynamics::{Aerodynamics, ConstantWind, GustingWind};
+use membrane::{CoRotational, StVK};
+
+fn usage(program: &str)
+{
+  println("Usage: " + program + " [options]");
+  println("  --frames N       number of frames to simulate (default: 100)");
+  println("  --timestep DT    timestep in seconds (default: 0.016)");
+  println("  --solver NAME    constraint solver: pgs, xpbd, implicit or pd (default: pgs)");
+  println("  --substeps N     number of substeps per frame (default: 1)");
+  println("  --gravity X,Y,Z  gravity vector (default: 0,0,-9.81)");
+  println("  --subdivs N      subdivisions of the simulated quad (default: 75)");
+  println("  --density D      mass per unit area of the cloth (default: 1)");
+  println("  --stretch K      stretch stiffness of the cloth (default: 50)");
+  println("  --bend K         bend stiffness of the cloth (default: 50)");
+  println("  --strain-limit S  keep the length of the edges within 1 - S and 1 + S times their");
+  println("                   rest length");
+  println("  --damping C      damping coefficient of the constraints (default: 0)");
+  println("  --rayleigh A,B   mass and stiffness proportional Rayleigh damping (default: 0,0)");
+  println("  --drag D         global linear drag (default: 0)");
+  println("  --bending NAME   bending model: springs or dihedral (default: springs)");
+  println("  --mesh FILE      simulate an OBJ or PLY triangle mesh instead of the quad");
+  println("  --tet FILE       simulate a solid from TetGen .node and .ele files instead of the quad");
+  println("  --volume K       volume stiffness of the tetrahedra of solids (default: 50)");
+  println("  --inflate P,K    keep the volume enclosed by a closed mesh at P times its initial");
+  println("                   value, with the stiffness K");
+  println("  --shape-matching D,S,B  shape matching on the blobs of edges within D of each other,");
+  println("                   with the stiffness S and the linear blend B");
+  println("  --membrane E,R,NU  finite element membrane with the Young's modulus E along the x");
+  println("                   axis, R times E along the other one, and the Poisson ratio NU");
+  println("                   (usually with --stretch 0)");
+  println("  --membrane-model NAME  membrane model: corot or stvk (default: corot)");
+  println("  --output FILE    file receiving the per-frame positions (default: positions.txt)");
+  println("  --pin-above Y    pin every vertex whose y coordinate is at least Y");
+  println("  --pin-boundary   pin every vertex on a boundary of the mesh");
+  println("  --ground Z       add a ground plane at height Z");
+  println("  --sphere X,Y,Z,R add a sphere of radius R centered at X,Y,Z");
+  println("  --friction MU    friction coefficient of the obstacles (default: 0.3)");
+  println("  --self-collision T  keep non-adjacent parts of the cloth at least T apart");
+  println("  --wind X,Y,Z     wind velocity, applied to the triangles of the cloth");
+  println("  --gusts A,F      make the wind speed fluctuate by a fraction A, F times per second");
+  println("  --tear STRAIN    tear the cloth where its strain exceeds STRAIN");
+  println("  --plastic Y,C,M  plastic deformation above the strain Y, with a creep rate C and a");
+  println("                   maximum plastic strain M");
+  println("  --obj PREFIX     also write every frame as PREFIX_NNNN.obj");
+  println("  --cache FILE     also record the frames in a binary cache");
+  println("  --compare FILE   compare the recorded cache with a reference cache");
+}
+
+fn parse_vec3(s: &str) -> Vec3<f64>
+{
+  let cs: ~[f64] = s.split_iter(',').transform(|c| {
+    match from_str::<f64>(c.trim())
+    {
+      Some(v) => v,
+      None    => fail!("Invalid vector component: " + c)
+    }
+  }).collect();
+
+  if cs.len() != 3
+  { fail!("Expected three comma-separated components, found: " + s) }
+
+  Vec3::new(cs[0], cs[1], cs[2])
+}
+
+fn write_frame(out: @io::Writer, frame: uint, soft_body: &SoftBody<f64, Vec3<f64>>)
+{
+  out.write_line("frame " + frame.to_str());
+
+  for p in soft_body.points.iter()
+  {
+    out.write_line(p.position.x.to_str() + " " +
+                   p.position.y.to_str() + " " +
+                   p.position.z.to_str());
+  }
+}
+
+#[main]
+fn main()
+{
+  let args    = os::args();
+  let program = args[0].clone();
+  let opts    = ~[
+    optopt("frames"),
+    optopt("timestep"),
+    optopt("gravity"),
+    optopt("substeps"),
+    optopt("solver"),
+    optopt("subdivs"),
+    optopt("mesh"),
+    optopt("tet"),
+    optopt("volume"),
+    optopt("inflate"),
+    optopt("shape-matching"),
+    optopt("membrane"),
+    optopt("membrane-model"),
+    optopt("density"),
+    optopt("stretch"),
+    optopt("bend"),
+    optopt("bending"),
+    optopt("strain-limit"),
+    optopt("damping"),
+    optopt("rayleigh"),
+    optopt("drag"),
+    optopt("output"),
+    optopt("pin-above"),
+    optflag("pin-boundary"),
+    optopt("ground"),
+    optopt("sphere"),
+    optopt("friction"),
+    optopt("self-collision"),
+    optopt("wind"),
+    optopt("gusts"),
+    optopt("tear"),
+    optopt("plastic"),
+    optopt("obj"),
+    optopt("cache"),
+    optopt("compare"),
+    optflag("help")
+  ];
+
+  let matches = match getopts(args.tail(), opts)
+  {
+    Ok(m)  => m,
+    Err(f) => fail!(fail_str(f))
+  };
+
+  if opt_present(&matches, "help")
+  {
+    usage(program);
+    return;
+  }
+
+  let nframes  = opt_maybe_str(&matches, "frames").map_default(100u, |s| from_str::<uint>(s.as_slice()).expect("Invalid frame count."));
+  let timestep = opt_maybe_str(&matches, "timestep").map_default(0.016f64, |s| from_str::<f64>(s.as_slice()).expect("Invalid timestep."));
+  let gravity  = opt_maybe_str(&matches, "gravity").map_default(Vec3::new(0.0f64, 0.0, -9.81), |s| parse_vec3(s.as_slice()));
+  let solver   = match opt_maybe_str(&matches, "solver")
+  {
+    None                                      => PGSSolver,
+    Some(ref s) if s.as_slice() == "pgs"      => PGSSolver,
+    Some(ref s) if s.as_slice() == "xpbd"     => XPBDSolver,
+    Some(ref s) if s.as_slice() == "implicit" => ImplicitEulerSolver,
+    Some(ref s) if s.as_slice() == "pd"       => ProjectiveDynamicsSolver,
+    Some(s)                                   => fail!("Unknown solver: " + s)
+  };
+  let substeps = opt_maybe_str(&matches, "substeps").map_default(1u, |s| from_str::<uint>(s.as_slice()).expect("Invalid substep count."));
+  let sub      = opt_maybe_str(&matches, "subdivs").map_default(75u, |s| from_str::<uint>(s.as_slice()).expect("Invalid subdivision count."));
+  let output   = opt_maybe_str(&matches, "output").map_default(~"positions.txt", |s| s.clone());
+
+  let tets = opt_maybe_str(&matches, "tet").map(|file| {
+    match loader::load_tetgen(&Path(*file))
+    {
+      Ok(t)  => t,
+      Err(e) => fail!(e)
+    }
+  });
+
+  let user_mesh = opt_maybe_str(&matches, "mesh").is_some() || tets.is_some();
+  let mesh = match opt_maybe_str(&matches, "mesh")
+  {
+    _ if tets.is_some() => tets.get_ref().surface_mesh(),
+    Some(file) =>
+    {
+      match loader::load(&Path(file))
+      {
+        Ok(m)  => m,
+        Err(e) => fail!(e)
+      }
+    },
+    None => primitives::quad(100.0, 100.0, sub, sub)
+  };
+
+  let default   = Material::default();
+  let density   = opt_maybe_str(&matches, "density").map_default(default.density, |s| from_str::<f64>(s.as_slice()).expect("Invalid density."));
+  let stretch   = opt_maybe_str(&matches, "stretch").map_default(default.stretch_stiffness, |s| from_str::<f64>(s.as_slice()).expect("Invalid stretch stiffness."));
+  let bend      = opt_maybe_str(&matches, "bend").map_default(default.bend_stiffness, |s| from_str::<f64>(s.as_slice()).expect("Invalid bend stiffness."));
+  let mut material = Material::new(density, stretch, bend);
+
+  material.volume_stiffness = opt_maybe_str(&matches, "volume").map_default(default.volume_stiffness, |s| from_str::<f64>(s.as_slice()).expect("Invalid volume stiffness."));
+
+  material.strain_limit = opt_maybe_str(&matches, "strain-limit").map_default(default.strain_limit, |s| from_str::<f64>(s.as_slice()).expect("Invalid strain limit."));
+
+  material.damping = opt_maybe_str(&matches, "damping").map_default(0.0, |s| from_str::<f64>(s.as_slice()).expect("Invalid damping coefficient."));
+
+  for rayleigh in opt_maybe_str(&matches, "rayleigh").iter()
+  {
+    let cs: ~[f64] = rayleigh.split_iter(',').transform(|c| from_str::<f64>(c.trim()).expect("Invalid Rayleigh coefficients.")).collect();
+
+    if cs.len() != 2
+    { fail!("Expected Rayleigh coefficients as A,B, found: " + *rayleigh) }
+
+    material.rayleigh_mass      = cs[0];
+    material.rayleigh_stiffness = cs[1];
+  }
+
+  let membrane = opt_maybe_str(&matches, "membrane").map(|s| parse_vec3(s.as_slice()));
+
+  for m in membrane.iter()
+  {
+    material.young_modulus = m.x;
+    material.weft_ratio    = m.y;
+    material.poisson_ratio = m.z;
+  }
+
+  let membrane_model = match opt_maybe_str(&matches, "membrane-model")
+  {
+    None                                   => CoRotational,
+    Some(ref s) if s.as_slice() == "corot" => CoRotational,
+    Some(ref s) if s.as_slice() == "stvk"  => StVK,
+    Some(s)                                => fail!("Unknown membrane model: " + s)
+  };
+
+  let materials = MaterialMap::new(&mesh, material);
+
+  let dihedral = match opt_maybe_str(&matches, "bending")
+  {
+    None                                      => false,
+    Some(ref s) if s.as_slice() == "springs"  => false,
+    Some(ref s) if s.as_slice() == "dihedral" => true,
+    Some(s)                                   => fail!("Unknown bending model: " + s)
+  };
+
+  let triangles  = mesh.ibuff.clone();
+  let shape_matching = opt_maybe_str(&matches, "shape-matching").map(|s| parse_vec3(s.as_slice()));
+  let blobs      = shape_matching.map(|sm| builder::blob_groups(mesh.clone(), sm.x as uint));
+  let boundaries = mesh.boundary_loops();
+  let (pairs, bending_stiffness) = builder::dihedral_parameters(&mesh, &materials);
+  let (elements, membrane_params) = builder::membrane_parameters(&mesh, &materials);
+  let (vertices, ids1, ids2, invmasses, stiffness) = match tets
+  {
+    Some(ref t) => builder::tet_soft_body_parameters(t, &materials),
+    None        =>
+    {
+      let (vertices, ids1, ids2, _, _, _, _, invmasses, stiffness) =
+        builder::soft_body_parameters(mesh.clone(), &materials, !dihedral, false);
+
+      (vertices, ids1, ids2, invmasses, stiffness)
+    }
+  };
+
+  let damping       = materials.constraint_damping(ids1, ids2, stiffness);
+  let strain_limits = match tets
+  {
+    Some(_) => materials.tet_strain_limits(ids1, ids2),
+    None    => materials.strain_limits(&mesh, ids1, ids2)
+  };
+  let mut soft_body = SoftBody::from_mesh_with_solver(vertices, ids1, ids2, invmasses, stiffness, solver);
+
+  soft_body.set_damping(damping, materials.point_damping());
+  soft_body.set_strain_limits(strain_limits);
+  soft_body.drag = opt_maybe_str(&matches, "drag").map_default(0.0, |s| from_str::<f64>(s.as_slice()).expect("Invalid drag."));
+
+  soft_body.set_triangles(triangles);
+
+  if dihedral
+  { soft_body.add_bending_constraints(pairs, bending_stiffness) }
+
+  if membrane.is_some()
+  { soft_body.add_membrane(elements, membrane_params, &Vec3::new(1.0, 0.0, 0.0), membrane_model) }
+
+  for t in tets.iter()
+  {
+    let (tetrahedra, volume_stiffness) = builder::volume_parameters(t, &materials);
+
+    soft_body.add_volume_constraints(tetrahedra, volume_stiffness);
+  }
+
+  for inflate in opt_maybe_str(&matches, "inflate").iter()
+  {
+    let cs: ~[f64] = inflate.split_iter(',').transform(|c| from_str::<f64>(c.trim()).expect("Invalid inflation.")).collect();
+
+    if cs.len() != 2
+    { fail!("Expected an inflation as P,K, found: " + *inflate) }
+
+    soft_body.inflate(cs[0], cs[1]);
+  }
+
+  match (shape_matching, blobs)
+  {
+    (Some(sm), Some(groups)) => soft_body.add_shape_matching_groups(groups, sm.y, sm.z),
+    _                        => { }
+  }
+  soft_body.stepper = Stepper::new(timestep, substeps);
+
+  for t in opt_maybe_str(&matches, "self-collision").iter()
+  { soft_body.enable_self_collision(from_str::<f64>(t.as_slice()).expect("Invalid thickness.")) }
+
+  for w in opt_maybe_str(&matches, "wind").iter()
+  {
+    let velocity = parse_vec3(w.as_slice());
+    let wind     = match opt_maybe_str(&matches, "gusts")
+    {
+      Some(g) =>
+      {
+        let cs: ~[f64] = g.split_iter(',').transform(|c| from_str::<f64>(c.trim()).expect("Invalid gusts.")).collect();
+
+        if cs.len() != 2
+        { fail!("Expected gusts as A,F, found: " + g) }
+
+        GustingWind(velocity, cs[0], cs[1])
+      },
+      None => ConstantWind(velocity)
+    };
+
+    soft_body.aerodynamics = Some(Aerodynamics::new(wind));
+  }
+
+  let tear = opt_maybe_str(&matches, "tear").map(|s| from_str::<f64>(s.as_slice()).expect("Invalid tearing strain."));
+
+  for strain in tear.iter()
+  { soft_body.set_break_thresholds(*strain, Bounded::max_value()) }
+
+  for plastic in opt_maybe_str(&matches, "plastic").iter()
+  {
+    let ps = parse_vec3(plastic.as_slice());
+
+    soft_body.set_plasticity(ps.x, ps.y, ps.z);
+  }
+
+  let pin_above    = opt_maybe_str(&matches, "pin-above").map(|s| from_str::<f64>(s.as_slice()).expect("Invalid pinning height."));
+  let pin_boundary = opt_present(&matches, "pin-boundary");
+
+  for y in pin_above.iter()
+  { soft_body.pin_where(|p| p.y >= *y); }
+
+  if pin_boundary
+  {
+    for boundary in boundaries.iter()
+    { soft_body.pin_all(*boundary) }
+  }
+
+  if !user_mesh && pin_above.is_none() && !pin_boundary
+  {
+    // same as the windowed demo: hold the two upper corners of the quad.
+    let nvertices = soft_body.points.len();
+    soft_body.pin(nvertices - 1);
+    soft_body.pin(nvertices - sub - 1);
+  }
+
+  let friction = opt_maybe_str(&matches, "friction").map_default(0.3f64, |s| from_str::<f64>(s.as_slice()).expect("Invalid friction coefficient."));
+
+  for z in opt_maybe_str(&matches, "ground").iter()
+  {
+    let height = from_str::<f64>(z.as_slice()).expect("Invalid ground height.");
+
+    soft_body.add_collider(Collider::new(Plane(Vec3::new(0.0f64, 0.0, 1.0)), Vec3::new(0.0, 0.0, height), friction));
+  }
+
+  for sphere in opt_maybe_str(&matches, "sphere").iter()
+  {
+    let cs: ~[f64] = sphere.split_iter(',').transform(|c| from_str::<f64>(c.trim()).expect("Invalid sphere.")).collect();
+
+    if cs.len() != 4
+    { fail!("Expected a sphere as X,Y,Z,R, found: " + *sphere) }
+
+    soft_body.add_collider(Collider::new(Sphere(cs[3]), Vec3::new(cs[0], cs[1], cs[2]), friction));
+  }
+
+  let out = match io::file_writer(&Path(output), [io::Create, io::Truncate])
+  {
+    Ok(w)  => w,
+    Err(e) => fail!("Unable to open the output file: " + e)
+  };
+
+  let mut obj_sequence = opt_maybe_str(&matches, "obj").map(|prefix| ObjSequence::new(prefix.clone(), triangles.clone()));
+  let cache_file       = opt_maybe_str(&matches, "cache");
+
+  if tear.is_some() && cache_file.is_some()
+  { fail!("--cache requires a constant vertex count and cannot be used with --tear.") }
+
+  let mut cache        = cache_file.map(|file| {
+    match CacheWriter::create(&Path(file.as_slice()), soft_body.points.len(), timestep)
+    {
+      Ok(c)  => c,
+      Err(e) => fail!("Unable to create the cache file: " + e)
+    }
+  });
+
+  let record = |soft_body: &SoftBody<f64, Vec3<f64>>, frame: uint| {
+    write_frame(out, frame, soft_body);
+
+    let positions = soft_body.positions();
+
+    for seq in obj_sequence.mut_iter()
+    {
+      // tearing changes the triangles
+      seq.triangles = soft_body.triangles.iter().transform(|&(a, b, c)| (a as u32, b as u32, c as u32)).collect();
+
+      match seq.write_frame(positions)
+      {
+        Ok(_)  => { },
+        Err(e) => fail!("Unable to write the OBJ frame: " + e)
+      }
+    }
+
+    for c in cache.mut_iter()
+    { c.write_frame(positions) }
+  };
+
+  record(&soft_body, 0);
+
+  let before = time::precise_time_s();
+
+  for frame in range(1u, nframes + 1)
+  {
+    soft_body.step(timestep, &gravity);
+
+    record(&soft_body, frame);
+  }
+
+  let elapsed = time::precise_time_s() - before;
+
+  println(nframes.to_str() + " frames simulated in " + elapsed.to_str() + " s");
+
+  for c in cache.mut_iter()
+  { c.finish() }
+
+  match (opt_maybe_str(&matches, "compare"), cache_file)
+  {
+    (Some(reference), Some(recorded)) =>
+    {
+      let c1 = Cache::load(&Path(reference)).unwrap();
+      let c2 = Cache::load(&Path(recorded)).unwrap();
+
+      match c1.max_distances(&c2)
+      {
+        Ok(dists) =>
+        {
+          for (i, d) in dists.iter().enumerate()
+          { println("frame " + i.to_str() + ": max deviation " + d.to_str()) }
+        },
+        Err(e) => fail!(e)
+      }
+    },
+    (Some(_), None) => fail!("--compare requires --cache."),
+    _ => { }
+  }
+}
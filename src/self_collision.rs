@@ -0,0 +1,428 @@
+use std::num::{Zero, One};
+use std::hashmap::{HashMap, HashSet};
+use nalgebra::traits::division_ring::DivisionRing;
+use nalgebra::traits::norm::Norm;
+use nalgebra::traits::dot::Dot;
+use nalgebra::traits::dim::Dim;
+use nalgebra::traits::indexable::Indexable;
+use nalgebra::traits::vector_space::VectorSpace;
+use soft_body::PointMass;
+
+/// Repulsion between a point and a triangle, or between two edges.
+///
+/// The normal velocity of the contact is interpolated from the velocities of its points with
+/// their barycentric weights, which are negative on the side pushed along `-normal`.
+pub struct SelfContact<N, V>
+{
+  ids:     ~[uint],
+  weights: ~[N],
+  normal:  V,
+  /// Minimal normal velocity, separating the two sides.
+  bias:    N,
+  impulse: N
+}
+
+/// Repulsion between non-adjacent parts of the same surface.
+///
+/// Points are tested against triangles and edges against edges, using a spatial hash with cells
+/// of size `cell_size`. A point and a triangle, or two edges, with vertices sharing a triangle are
+/// neighbours on the surface and never collide: their distance at rest may be below the
+/// thickness.
+pub struct SelfCollision<N>
+{
+  thickness:       N,
+  cell_size:       N,
+  priv edges:      ~[(uint, uint)],
+  /// Vertices sharing a triangle with each vertex.
+  priv neighbours: ~[~[uint]]
+}
+
+impl<N: DivisionRing + NumCast + Signed + Orderable + Bounded + Round + Clone> SelfCollision<N>
+{
+  pub fn new(triangles: &[(uint, uint, uint)], thickness: N, cell_size: N) -> SelfCollision<N>
+  {
+    let mut edges      = ~[];
+    let mut seen       = HashSet::new();
+    let mut neighbours = ~[];
+
+    for &(a, b, c) in triangles.iter()
+    {
+      for &(e1, e2) in [(a, b), (b, c), (c, a)].iter()
+      {
+        let e = if e1 < e2 { (e1, e2) } else { (e2, e1) };
+
+        if seen.insert(e)
+        {
+          edges.push(e);
+
+          let (lo, hi) = e;
+
+          while neighbours.len() <= hi
+          { neighbours.push(~[]) }
+
+          neighbours[lo].push(hi);
+          neighbours[hi].push(lo);
+        }
+      }
+    }
+
+    SelfCollision {
+      thickness:  thickness,
+      cell_size:  cell_size,
+      edges:      edges,
+      neighbours: neighbours
+    }
+  }
+
+  // Whether the vertices `a` and `b` are the same or share a triangle.
+  fn adjacent(&self, a: uint, b: uint) -> bool
+  { a == b || (a < self.neighbours.len() && self.neighbours[a].contains(&b)) }
+
+  /// Generates the point-triangle and edge-edge contacts, to be resolved with `solve_contacts`.
+  pub fn collect_contacts<V: VectorSpace<N> + Norm<N> + Dot<N> + Indexable<uint, N> + Dim + Clone>(
+                          &self,
+                          points:    &[PointMass<N, V>],
+                          triangles: &[(uint, uint, uint)],
+                          dt:        &N,
+                          out:       &mut ~[SelfContact<N, V>])
+  {
+    let erp: N      = NumCast::from::<N, float>(0.4);
+    let thickness   = self.thickness.clone();
+
+    // pushes `ids1` along `n` and `ids2` along `-n`, with the given barycentric weights.
+    let push = |ids1: &[uint], ws1: &[N], ids2: &[uint], ws2: &[N], n: &V, dist: &N| {
+      let mut ids     = ids1.to_owned();
+      let mut weights = ws1.to_owned();
+
+      ids.push_all(ids2);
+
+      for w in ws2.iter()
+      { weights.push(-*w) }
+
+      out.push(SelfContact {
+        ids:     ids,
+        weights: weights,
+        normal:  n.clone(),
+        bias:    (thickness - *dist) * erp / *dt,
+        impulse: Zero::zero()
+      })
+    };
+
+    /*
+     * Point-triangle
+     */
+    let mut tri_cells: HashMap<int, ~[uint]> = HashMap::new();
+
+    for (t, &(a, b, c)) in triangles.iter().enumerate()
+    {
+      let (min, max) = self.cell_range([&points[a].position, &points[b].position, &points[c].position]);
+
+      do for_each_cell(min, max) |key|
+      { tri_cells.find_or_insert(key, ~[]).push(t) }
+    }
+
+    for (i, p) in points.iter().enumerate()
+    {
+      let cell = self.cell(&p.position);
+
+      match tri_cells.find(&cell_key(cell))
+      {
+        Some(ts) =>
+        {
+          for t in ts.iter()
+          {
+            let (a, b, c) = triangles[*t];
+
+            if self.adjacent(i, a) || self.adjacent(i, b) || self.adjacent(i, c)
+            { loop }
+
+            let (wa, wb, wc) = closest_on_triangle(&p.position,
+                                                   &points[a].position,
+                                                   &points[b].position,
+                                                   &points[c].position);
+            let q        = points[a].position.scalar_mul(&wa) +
+                           points[b].position.scalar_mul(&wb) +
+                           points[c].position.scalar_mul(&wc);
+            let mut n    = p.position - q;
+            let     dist = n.normalize();
+
+            if dist < thickness && !dist.is_zero()
+            { push([i], [One::one()], [a, b, c], [wa, wb, wc], &n, &dist) }
+          }
+        },
+        None => { }
+      }
+    }
+
+    /*
+     * Edge-edge
+     */
+    let mut edge_cells: HashMap<int, ~[uint]> = HashMap::new();
+
+    for (e, &(a, b)) in self.edges.iter().enumerate()
+    {
+      let (min, max) = self.cell_range([&points[a].position, &points[b].position]);
+
+      do for_each_cell(min, max) |key|
+      { edge_cells.find_or_insert(key, ~[]).push(e) }
+    }
+
+    let mut tested = HashSet::new();
+
+    for (_, es) in edge_cells.iter()
+    {
+      for e1 in es.iter()
+      {
+        for e2 in es.iter()
+        {
+          if *e1 >= *e2 || !tested.insert((*e1, *e2))
+          { loop }
+
+          let (a0, a1) = self.edges[*e1];
+          let (b0, b1) = self.edges[*e2];
+
+          if self.adjacent(a0, b0) || self.adjacent(a0, b1) ||
+             self.adjacent(a1, b0) || self.adjacent(a1, b1)
+          { loop }
+
+          let (s, t) = closest_on_segments(&points[a0].position, &points[a1].position,
+                                           &points[b0].position, &points[b1].position);
+          let pa       = points[a0].position + (points[a1].position - points[a0].position).scalar_mul(&s);
+          let pb       = points[b0].position + (points[b1].position - points[b0].position).scalar_mul(&t);
+          let mut n    = pa - pb;
+          let     dist = n.normalize();
+
+          if dist < thickness && !dist.is_zero()
+          {
+            let _1 = One::one::<N>();
+
+            push([a0, a1], [_1 - s, s.clone()], [b0, b1], [_1 - t, t.clone()], &n, &dist)
+          }
+        }
+      }
+    }
+  }
+
+  fn cell<V: Indexable<uint, N> + Dim>(&self, p: &V) -> ~[int]
+  {
+    let mut res = ~[];
+
+    for d in range(0u, Dim::dim::<V>())
+    { res.push((p.at(d) / self.cell_size).floor().to_int()) }
+
+    res
+  }
+
+  // Cells covered by the bounding box of `ps`, enlarged by the thickness.
+  fn cell_range<V: Indexable<uint, N> + Dim>(&self, ps: &[&V]) -> (~[int], ~[int])
+  {
+    let mut min = ~[];
+    let mut max = ~[];
+
+    for d in range(0u, Dim::dim::<V>())
+    {
+      let mut lo = Bounded::max_value::<N>();
+      let mut hi = -Bounded::max_value::<N>();
+
+      for p in ps.iter()
+      {
+        lo = lo.min(&p.at(d));
+        hi = hi.max(&p.at(d));
+      }
+
+      min.push(((lo - self.thickness) / self.cell_size).floor().to_int());
+      max.push(((hi + self.thickness) / self.cell_size).floor().to_int());
+    }
+
+    (min, max)
+  }
+}
+
+/// One projected Gauss-Seidel sweep over the contacts: the interpolated normal velocity of each
+/// contact is driven to at least its bias, with one impulse accumulated per contact over the
+/// sweeps. `fext_dt` is the velocity change due to external forces at the next integration.
+///
+/// The sweeps are alternated with the iterations of the other constraints by
+/// `soft_body::gauss_seidel_solve`.
+pub fn solve_contacts<N: DivisionRing + Orderable + Clone, V: VectorSpace<N> + Dot<N> + Clone>(
+                      points:   &mut [PointMass<N, V>],
+                      contacts: &mut [SelfContact<N, V>],
+                      fext_dt:  &V)
+{
+  for c in contacts.mut_iter()
+  {
+    let mut vn    = Zero::zero::<N>();
+    let mut denom = Zero::zero::<N>();
+
+    for (i, w) in c.ids.iter().zip(c.weights.iter())
+    {
+      let p = &points[*i];
+
+      // a pinned point is not pushed, but its velocity counts in the relative velocity.
+      if p.invmass.is_zero()
+      { vn = vn + *w * c.normal.dot(&p.velocity) }
+      else
+      {
+        vn    = vn + *w * c.normal.dot(&(p.velocity + *fext_dt));
+        denom = denom + *w * *w * p.invmass;
+      }
+    }
+
+    if denom.is_zero()
+    { loop }
+
+    // the contact can only push
+    let impulse = (c.impulse + (c.bias - vn) / denom).max(&Zero::zero());
+    let delta   = impulse - c.impulse;
+
+    c.impulse = impulse;
+
+    for (i, w) in c.ids.iter().zip(c.weights.iter())
+    {
+      let p = &mut points[*i];
+
+      if !p.invmass.is_zero()
+      { p.velocity = p.velocity + c.normal.scalar_mul(&(*w * p.invmass * delta)) }
+    }
+  }
+}
+
+fn cell_key(cell: &[int]) -> int
+{
+  static PRIMES: [int, ..3] = [73856093, 19349663, 83492791];
+
+  let mut key = 0;
+
+  for (i, c) in cell.iter().enumerate()
+  { key = key ^ (*c * PRIMES[i % 3]) }
+
+  key
+}
+
+fn for_each_cell(min: &[int], max: &[int], f: &fn(int))
+{
+  let mut cell = min.to_owned();
+
+  loop
+  {
+    f(cell_key(cell));
+
+    // odometer increment
+    let mut d = 0;
+
+    while d < cell.len() && cell[d] == max[d]
+    {
+      cell[d] = min[d];
+      d = d + 1;
+    }
+
+    if d == cell.len()
+    { break }
+
+    cell[d] = cell[d] + 1;
+  }
+}
+
+// Barycentric coordinates of the point of the triangle `abc` closest to `p`.
+fn closest_on_triangle<N: DivisionRing + Ord + Clone, V: VectorSpace<N> + Dot<N>>(p: &V, a: &V, b: &V, c: &V) -> (N, N, N)
+{
+  let _0 = Zero::zero::<N>();
+  let _1 = One::one::<N>();
+
+  let ab = *b - *a;
+  let ac = *c - *a;
+  let ap = *p - *a;
+  let d1 = ab.dot(&ap);
+  let d2 = ac.dot(&ap);
+
+  if d1 <= _0 && d2 <= _0
+  { return (_1, _0.clone(), _0) }
+
+  let bp = *p - *b;
+  let d3 = ab.dot(&bp);
+  let d4 = ac.dot(&bp);
+
+  if d3 >= _0 && d4 <= d3
+  { return (_0.clone(), _1, _0) }
+
+  let vc = d1 * d4 - d3 * d2;
+
+  if vc <= _0 && d1 >= _0 && d3 <= _0
+  {
+    let v = d1 / (d1 - d3);
+    return (_1 - v, v, _0)
+  }
+
+  let cp = *p - *c;
+  let d5 = ab.dot(&cp);
+  let d6 = ac.dot(&cp);
+
+  if d6 >= _0 && d5 <= d6
+  { return (_0.clone(), _0, _1) }
+
+  let vb = d5 * d2 - d1 * d6;
+
+  if vb <= _0 && d2 >= _0 && d6 <= _0
+  {
+    let w = d2 / (d2 - d6);
+    return (_1 - w, _0, w)
+  }
+
+  let va = d3 * d6 - d5 * d4;
+
+  if va <= _0 && (d4 - d3) >= _0 && (d5 - d6) >= _0
+  {
+    let w = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+    return (_0, _1 - w, w)
+  }
+
+  let denom = _1 / (va + vb + vc);
+  let v     = vb * denom;
+  let w     = vc * denom;
+
+  (_1 - v - w, v, w)
+}
+
+// Parameters of the closest points of the segments `[p1, q1]` and `[p2, q2]`.
+fn closest_on_segments<N: DivisionRing + Orderable + Clone, V: VectorSpace<N> + Dot<N>>(p1: &V, q1: &V, p2: &V, q2: &V) -> (N, N)
+{
+  let _0 = Zero::zero::<N>();
+  let _1 = One::one::<N>();
+  let clamp = |x: N| x.max(&_0).min(&_1);
+
+  let d1 = *q1 - *p1;
+  let d2 = *q2 - *p2;
+  let r  = *p1 - *p2;
+  let a  = d1.dot(&d1);
+  let e  = d2.dot(&d2);
+  let f  = d2.dot(&r);
+
+  if a.is_zero() && e.is_zero()
+  { return (_0.clone(), _0) }
+
+  if a.is_zero()
+  { return (_0.clone(), clamp(f / e)) }
+
+  let c = d1.dot(&r);
+
+  if e.is_zero()
+  { return (clamp(-c / a), _0) }
+
+  let b     = d1.dot(&d2);
+  let denom = a * e - b * b;
+  let mut s = if denom.is_zero() { _0.clone() } else { clamp((b * f - c * e) / denom) };
+  let mut t = (b * s + f) / e;
+
+  if t < _0
+  {
+    t = _0.clone();
+    s = clamp(-c / a);
+  }
+  else if t > _1
+  {
+    t = _1.clone();
+    s = clamp((b - c) / a);
+  }
+
+  (s, t)
+}
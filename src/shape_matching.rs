@@ -0,0 +1,293 @@
+use std::num::{Zero, One};
+use nalgebra::traits::division_ring::DivisionRing;
+use nalgebra::traits::norm::Norm;
+use nalgebra::traits::dot::Dot;
+use nalgebra::traits::cross::Cross;
+use nalgebra::traits::dim::Dim;
+use nalgebra::traits::indexable::Indexable;
+use nalgebra::traits::vector_space::VectorSpace;
+use soft_body::SoftBody;
+
+// Iterations of the extraction of the rotation, warm-started with the previous rotation.
+static ROTATION_ITERATIONS: uint = 10;
+
+/// Group of points pulled toward the best-fit transform of their rest configuration (Müller et
+/// al., Meshless Deformations Based on Shape Matching).
+///
+/// Matrices are stored as lists of columns.
+pub struct ShapeMatchingGroup<N, V>
+{
+  ids:       ~[uint],
+  /// Rest positions, relative to the rest center of mass.
+  rest:      ~[V],
+  /// Masses at creation, the pinned points weighting as much as the heaviest point.
+  masses:    ~[N],
+  /// Fraction of the way toward the goal positions travelled at each substep, between 0 and 1.
+  stiffness: N,
+  /// Blend between the rigid transform (0) and the best-fit linear transform (1).
+  beta:      N,
+  priv rotation: ~[V],
+  priv inv_aqq:  ~[V]
+}
+
+impl<V: VectorSpace<N> + Dot<N> + Norm<N> + Cross<V> + Indexable<uint, N> + Dim + Clone + ToStr,
+     N:  DivisionRing + Orderable + NumCast + Signed + Bounded + Round + Trigonometric + Ord + ToStr + Eq + Clone>
+     SoftBody<N, V>
+{
+  /// Makes the points `ids` match the shape they have now. Returns the index of the group.
+  pub fn add_shape_matching_group(&mut self, ids: ~[uint], stiffness: N, beta: N) -> uint
+  {
+    let mut masses: ~[N] = ids.iter().transform(|i| {
+      let w = &self.points[*i].invmass;
+
+      if w.is_zero() { Zero::zero() } else { One::one::<N>() / *w }
+    }).collect();
+
+    let heaviest = masses.iter().fold(One::one::<N>(), |m, w| m.max(w));
+
+    for m in masses.mut_iter()
+    {
+      if m.is_zero()
+      { *m = heaviest.clone() }
+    }
+
+    let positions: ~[V] = ids.iter().transform(|i| self.points[*i].position.clone()).collect();
+    let center           = center_of_mass(positions, masses);
+    let rest: ~[V]       = positions.iter().transform(|p| *p - center).collect();
+
+    // A_qq = sum(m q q^t), regularized for flat groups
+    let dim     = Dim::dim::<V>();
+    let mut aqq = outer_sum(rest, rest, masses);
+    let mut tr  = Zero::zero::<N>();
+
+    for d in range(0u, dim)
+    { tr = tr + aqq[d].at(d) }
+
+    let eps = tr * NumCast::from::<N, float>(1.0e-4) / NumCast::from::<N, uint>(dim);
+
+    for d in range(0u, dim)
+    {
+      let v = aqq[d].at(d) + eps;
+
+      aqq[d].set(d, v)
+    }
+
+    self.shape_groups.push(ShapeMatchingGroup {
+      ids:       ids,
+      rest:      rest,
+      masses:    masses,
+      stiffness: stiffness,
+      beta:      beta,
+      rotation:  identity::<N, V>(),
+      inv_aqq:   inverse(aqq)
+    });
+
+    self.shape_groups.len() - 1
+  }
+
+  /// Adds a shape matching group for each set of points of `groups`, as given by
+  /// `builder::blob_groups`.
+  pub fn add_shape_matching_groups(&mut self, groups: ~[~[uint]], stiffness: N, beta: N)
+  {
+    for g in groups.consume_iter()
+    {
+      if g.len() > 1
+      { self.add_shape_matching_group(g, stiffness.clone(), beta.clone()); }
+    }
+  }
+
+  pub fn remove_shape_matching_group(&mut self, i: uint) -> ShapeMatchingGroup<N, V>
+  { self.shape_groups.remove(i) }
+
+  /// Moves the points of each group toward their goal positions, and updates their velocities
+  /// accordingly.
+  pub fn project_shape_matching(&mut self, dt: &N)
+  {
+    let _1 = One::one::<N>();
+
+    for g in self.shape_groups.mut_iter()
+    {
+      let mut positions = ~[];
+
+      for i in g.ids.iter()
+      { positions.push(self.points[*i].position.clone()) }
+
+      let center          = center_of_mass(positions, g.masses);
+      let relative: ~[V]  = positions.iter().transform(|p| *p - center).collect();
+
+      // A_pq = sum(m p q^t)
+      let apq = outer_sum(relative, g.rest, g.masses);
+
+      extract_rotation(apq, g.rotation);
+
+      let transform = if g.beta.is_zero()
+      { g.rotation.clone() }
+      else
+      {
+        let linear = mul(apq, g.inv_aqq);
+
+        linear.iter().zip(g.rotation.iter()).transform(|(a, r)| {
+          a.scalar_mul(&g.beta) + r.scalar_mul(&(_1 - g.beta))
+        }).collect()
+      };
+
+      for (k, i) in g.ids.iter().enumerate()
+      {
+        let p = &mut self.points[*i];
+
+        if p.invmass.is_zero()
+        { loop }
+
+        let goal = center + apply(transform, &g.rest[k]);
+        let dx   = (goal - p.position).scalar_mul(&g.stiffness);
+
+        p.position = p.position + dx;
+        p.velocity = p.velocity + dx.scalar_div(dt);
+      }
+    }
+  }
+}
+
+fn center_of_mass<N: DivisionRing + Clone, V: VectorSpace<N> + Clone>(positions: &[V], masses: &[N]) -> V
+{
+  let mut center = Zero::zero::<V>();
+  let mut total  = Zero::zero::<N>();
+
+  for (p, m) in positions.iter().zip(masses.iter())
+  {
+    center = center + p.scalar_mul(m);
+    total  = total + *m;
+  }
+
+  center.scalar_div(&total)
+}
+
+// sum(m a b^t), as columns.
+fn outer_sum<N: DivisionRing + Clone, V: VectorSpace<N> + Indexable<uint, N> + Dim + Clone>(
+             a: &[V], b: &[V], masses: &[N]) -> ~[V]
+{
+  range(0u, Dim::dim::<V>()).transform(|j| {
+    let mut col = Zero::zero::<V>();
+
+    for ((x, y), m) in a.iter().zip(b.iter()).zip(masses.iter())
+    { col = col + x.scalar_mul(&(*m * y.at(j))) }
+
+    col
+  }).collect()
+}
+
+fn identity<N: DivisionRing, V: VectorSpace<N> + Indexable<uint, N> + Dim>() -> ~[V]
+{
+  range(0u, Dim::dim::<V>()).transform(|j| {
+    let mut col = Zero::zero::<V>();
+
+    col.set(j, One::one());
+
+    col
+  }).collect()
+}
+
+fn apply<N: DivisionRing + Clone, V: VectorSpace<N> + Indexable<uint, N> + Clone>(m: &[V], v: &V) -> V
+{
+  let mut res = Zero::zero::<V>();
+
+  for (j, col) in m.iter().enumerate()
+  { res = res + col.scalar_mul(&v.at(j)) }
+
+  res
+}
+
+fn mul<N: DivisionRing + Clone, V: VectorSpace<N> + Indexable<uint, N> + Clone>(a: &[V], b: &[V]) -> ~[V]
+{ b.iter().transform(|col| apply(a, col)).collect() }
+
+// Gauss-Jordan elimination with partial pivoting. The matrix must be invertible.
+fn inverse<N: DivisionRing + Signed + Ord + Clone, V: VectorSpace<N> + Indexable<uint, N> + Dim + Clone>(
+           m: ~[V]) -> ~[V]
+{
+  let dim     = Dim::dim::<V>();
+  let mut a   = m;
+  let mut inv = identity::<N, V>();
+
+  // row operations are done on every column at once
+  for c in range(0u, dim)
+  {
+    let mut pivot = c;
+
+    for r in range(c + 1, dim)
+    {
+      if a[c].at(r).abs() > a[c].at(pivot).abs()
+      { pivot = r }
+    }
+
+    for col in a.mut_iter().chain_(inv.mut_iter())
+    {
+      let (x, y) = (col.at(c), col.at(pivot));
+
+      col.set(c, y);
+      col.set(pivot, x);
+    }
+
+    let d = a[c].at(c);
+
+    for col in a.mut_iter().chain_(inv.mut_iter())
+    {
+      let v = col.at(c) / d;
+
+      col.set(c, v)
+    }
+
+    for r in range(0u, dim)
+    {
+      if r == c
+      { loop }
+
+      let f = a[c].at(r);
+
+      for col in a.mut_iter().chain_(inv.mut_iter())
+      {
+        let v = col.at(r) - f * col.at(c);
+
+        col.set(r, v)
+      }
+    }
+  }
+
+  inv
+}
+
+// Rotational part of `a`, refined from `rotation` (Müller et al., A Robust Method to Extract the
+// Rotational Part of Deformations). Unlike a polar decomposition, it handles rank-deficient
+// matrices, e.g. for flat groups.
+fn extract_rotation<N: DivisionRing + Signed + Ord + Trigonometric + NumCast + Clone,
+                    V: VectorSpace<N> + Dot<N> + Norm<N> + Cross<V> + Clone>(
+                    a: &[V], rotation: &mut [V])
+{
+  let eps = NumCast::from::<N, float>(1.0e-9);
+
+  for _ in range(0u, ROTATION_ITERATIONS)
+  {
+    let mut omega = Zero::zero::<V>();
+    let mut denom = Zero::zero::<N>();
+
+    for (r, c) in rotation.iter().zip(a.iter())
+    {
+      omega = omega + r.cross(c);
+      denom = denom + r.dot(c);
+    }
+
+    let mut axis  = omega.scalar_div(&(denom.abs() + eps));
+    let     angle = axis.normalize();
+
+    if angle < eps
+    { break }
+
+    // Rodrigues' rotation of each column
+    let (sin, cos) = (angle.sin(), angle.cos());
+
+    for r in rotation.mut_iter()
+    {
+      *r = r.scalar_mul(&cos) + axis.cross(r).scalar_mul(&sin) +
+           axis.scalar_mul(&(axis.dot(r) * (One::one::<N>() - cos)))
+    }
+  }
+}
@@ -0,0 +1,192 @@
+use std::vec;
+use std::num::Zero;
+use extra::sort;
+use nalgebra::traits::division_ring::DivisionRing;
+use nalgebra::traits::vector_space::VectorSpace;
+
+/// `L D L^t` factorization of a sparse symmetric positive definite matrix, stored as a skyline:
+/// each row of `L` is kept from its first non-zero entry to the diagonal. Rows and columns are
+/// reordered with reverse Cuthill-McKee to keep the skyline narrow.
+pub struct SkylineLDLT<N>
+{
+  priv perm:     ~[uint], // new index -> original index
+  priv inv_perm: ~[uint], // original index -> new index
+  priv first:    ~[uint],
+  priv offsets:  ~[uint],
+  priv lower:    ~[N],
+  priv diag:     ~[N]
+}
+
+impl<N: DivisionRing + Clone> SkylineLDLT<N>
+{
+  /// Factorizes the `n x n` matrix given by its lower triangular entries `(row, column, value)`,
+  /// with `row >= column`. Duplicate entries are summed.
+  pub fn new(n: uint, entries: &[(uint, uint, N)]) -> SkylineLDLT<N>
+  {
+    let (perm, inv_perm) = reverse_cuthill_mckee(n, entries);
+
+    // skyline of the permuted matrix
+    let mut first = vec::from_fn(n, |i| i);
+
+    for &(i, j, _) in entries.iter()
+    {
+      let (pi, pj) = (inv_perm[i], inv_perm[j]);
+      let (r, c)   = if pi >= pj { (pi, pj) } else { (pj, pi) };
+
+      if c < first[r]
+      { first[r] = c }
+    }
+
+    let mut offsets = ~[];
+    let mut size    = 0u;
+
+    for i in range(0u, n)
+    {
+      offsets.push(size);
+      size = size + i - first[i];
+    }
+
+    let mut lower = vec::from_elem(size, Zero::zero::<N>());
+    let mut diag  = vec::from_elem(n, Zero::zero::<N>());
+
+    for &(i, j, ref v) in entries.iter()
+    {
+      let (pi, pj) = (inv_perm[i], inv_perm[j]);
+      let (r, c)   = if pi >= pj { (pi, pj) } else { (pj, pi) };
+
+      if r == c
+      { diag[r] = diag[r] + *v }
+      else
+      {
+        let id = offsets[r] + c - first[r];
+
+        lower[id] = lower[id] + *v;
+      }
+    }
+
+    // in-place factorization, row by row
+    for i in range(0u, n)
+    {
+      for j in range(first[i], i)
+      {
+        let mut s = lower[offsets[i] + j - first[i]].clone();
+
+        for k in range(first[i].max(&first[j]), j)
+        { s = s - lower[offsets[i] + k - first[i]] * diag[k] * lower[offsets[j] + k - first[j]] }
+
+        lower[offsets[i] + j - first[i]] = s / diag[j];
+      }
+
+      for k in range(first[i], i)
+      {
+        let l = lower[offsets[i] + k - first[i]].clone();
+
+        diag[i] = diag[i] - l * l * diag[k];
+      }
+
+      assert!(!diag[i].is_zero(), "The matrix is singular.");
+    }
+
+    SkylineLDLT {
+      perm:     perm,
+      inv_perm: inv_perm,
+      first:    first,
+      offsets:  offsets,
+      lower:    lower,
+      diag:     diag
+    }
+  }
+
+  /// Solves the factorized system for a right hand side with vector entries: each coordinate is
+  /// solved independently.
+  pub fn solve<V: VectorSpace<N> + Clone>(&self, b: &[V]) -> ~[V]
+  {
+    let n     = self.diag.len();
+    let mut x = vec::from_fn(n, |i| b[self.perm[i]].clone());
+
+    // L y = b
+    for i in range(0u, n)
+    {
+      for k in range(self.first[i], i)
+      { x[i] = x[i] - x[k].scalar_mul(&self.lower[self.offsets[i] + k - self.first[i]]) }
+    }
+
+    // D z = y
+    for i in range(0u, n)
+    { x[i] = x[i].scalar_div(&self.diag[i]) }
+
+    // L^t x = z
+    let mut i = n;
+
+    while i != 0
+    {
+      i = i - 1;
+
+      for k in range(self.first[i], i)
+      { x[k] = x[k] - x[i].scalar_mul(&self.lower[self.offsets[i] + k - self.first[i]]) }
+    }
+
+    vec::from_fn(n, |i| x[self.inv_perm[i]].clone())
+  }
+}
+
+// Reverse Cuthill-McKee ordering of the adjacency graph of the matrix. Returns the permutation
+// and its inverse.
+fn reverse_cuthill_mckee<N>(n: uint, entries: &[(uint, uint, N)]) -> (~[uint], ~[uint])
+{
+  let mut adj = vec::from_elem(n, ~[]);
+
+  for &(i, j, _) in entries.iter()
+  {
+    if i != j && !adj[i].contains(&j)
+    {
+      adj[i].push(j);
+      adj[j].push(i);
+    }
+  }
+
+  let degrees: ~[uint] = adj.iter().transform(|a| a.len()).collect();
+  let mut visited      = vec::from_elem(n, false);
+  let mut order        = ~[];
+
+  while order.len() != n
+  {
+    // each connected component starts from one of its vertices of minimal degree
+    let mut start = n;
+
+    for i in range(0u, n)
+    {
+      if !visited[i] && (start == n || degrees[i] < degrees[start])
+      { start = i }
+    }
+
+    visited[start] = true;
+    order.push(start);
+
+    let mut head = order.len() - 1;
+
+    while head != order.len()
+    {
+      let mut neighbors: ~[uint] = adj[order[head]].iter().filter(|j| !visited[**j]).transform(|j| *j).collect();
+
+      sort::quick_sort(neighbors, |a, b| degrees[*a] <= degrees[*b]);
+
+      for j in neighbors.iter()
+      {
+        visited[*j] = true;
+        order.push(*j);
+      }
+
+      head = head + 1;
+    }
+  }
+
+  order.reverse();
+
+  let mut inv = vec::from_elem(n, 0u);
+
+  for (new, old) in order.iter().enumerate()
+  { inv[*old] = new }
+
+  (order, inv)
+}
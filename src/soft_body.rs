@@ -1,37 +1,137 @@
+use std::vec;
 use std::num::{Zero, One};
-use nalgebra::vec::Vec1;
 use nalgebra::traits::division_ring::DivisionRing;
 use nalgebra::traits::norm::Norm;
 use nalgebra::traits::dot::Dot;
+use nalgebra::traits::cross::Cross;
+use nalgebra::traits::dim::Dim;
+use nalgebra::traits::indexable::Indexable;
 use nalgebra::traits::vector_space::VectorSpace;
 use nphysics::resolution::constraint::velocity_constraint::VelocityConstraint;
 use nphysics::resolution::constraint::projected_gauss_seidel_solver::projected_gauss_seidel_solve;
+use pin::Pin;
+use attachment::Anchor;
+use collision::Collider;
+use self_collision::SelfCollision;
+use self_collision;
+use stepper::Stepper;
+use projective::ProjectiveSystem;
+use aerodynamics::Aerodynamics;
+use force_field::ForceField;
+use rigid_coupling::{RigidBodyProxy, RigidAttachment};
+use bending::{BendingConstraint, dihedral_angle};
+use volume::{VolumeConstraint, InflationConstraint};
+use shape_matching::ShapeMatchingGroup;
+use membrane::MembraneElement;
+use projection::VelocityProjection;
+use projection;
 
+#[deriving(Clone)]
 pub struct PointMass<N, V>
 {
-  invmass:    N,
-  velocity:   V,
-  position:   V,
+  invmass:       N,
+  /// Rate at which the velocity decays, per second, in addition to `SoftBody::drag`.
+  damping:       N,
+  velocity:      V,
+  position:      V,
+  /// Position before the last integration.
+  last_position: V
 }
 
 pub struct ConstraintsGeometry<N>
 {
   stiffness:   N,
+  /// Inverse of the stiffness, used by the position-based solver.
+  compliance:  N,
   rest_length: N,
+  /// Rest length before any plastic deformation.
+  initial_rest_length: N,
+  /// Strain above which the rest length creeps toward the current length (see
+  /// `SoftBody::set_plasticity`).
+  yield_strain: N,
+  /// Fraction of the strain exceeding `yield_strain` turned into plastic deformation per second.
+  creep:        N,
+  /// Maximum relative deviation of the rest length from `initial_rest_length`.
+  max_plastic_strain: N,
+  /// Force opposing the relative velocity of the two points along the constraint, per unit of
+  /// velocity.
+  damping:     N,
   impulse:     N,
+  /// Strain above which the constraint breaks (see `SoftBody::tear`).
+  break_strain:  N,
+  /// Impulse magnitude above which the constraint breaks.
+  break_impulse: N,
+  /// Maximum strain, in absolute value, let through by `SoftBody::limit_strain`.
+  strain_limit:  N,
   rb1:         uint,
   rb2:         uint
 }
 
+/// Formulation used by `SoftBody::solve`.
+#[deriving(Eq, Clone)]
+pub enum SolverKind
+{
+  /// Velocity-level projected Gauss-Seidel, from nphysics.
+  PGSSolver,
+  /// Extended position-based dynamics: constraints are projected on the positions, their
+  /// softness being given by their compliance.
+  XPBDSolver,
+  /// Backward Euler integration of the springs, solved with a preconditioned conjugate gradient.
+  /// Stable for stiff springs and large timesteps.
+  ImplicitEulerSolver,
+  /// Projective Dynamics: local projections of the springs alternated with global solves using
+  /// a matrix factorized on the first solve, and reused while the pins and the timestep stay the
+  /// same.
+  ProjectiveDynamicsSolver
+}
+
 pub struct SoftBody<N, V>
 {
   ext_forces:  V,
+  time:        N,
+  /// Global linear drag: rate at which the velocity of every point decays, per second.
+  drag:        N,
+  solver:      SolverKind,
+  iterations:  uint,
+  /// Maximum number of iterations of the strain limiting pass.
+  strain_iterations: uint,
+  /// Strain excess, relative to the rest length, under which the strain limiting pass stops.
+  strain_tolerance:  N,
   points:      ~[PointMass<N, V>],
-  constraints: ~[ConstraintsGeometry<N>]
+  constraints: ~[ConstraintsGeometry<N>],
+  bendings:    ~[BendingConstraint<N>],
+  /// Finite elements of the membrane, applied at integration.
+  membrane:    ~[MembraneElement<N>],
+  /// Volume constraints on the tetrahedra of solids.
+  volumes:     ~[VolumeConstraint<N>],
+  /// Constraint on the volume enclosed by the triangles, for closed surfaces.
+  inflation:   Option<InflationConstraint<N>>,
+  /// Groups of points pulled toward the best-fit transform of their rest shape.
+  shape_groups: ~[ShapeMatchingGroup<N, V>],
+  pins:        ~[Pin<N, V>],
+  /// Compliant attachments of points to locations of the world.
+  anchors:     ~[Anchor<N, V>],
+  colliders:   ~[Collider<N, V>],
+  /// Wind forces on the triangles, applied at integration. Requires the triangles.
+  aerodynamics: Option<Aerodynamics<N, V>>,
+  /// Per-point external forces, applied at integration.
+  force_fields: ~[@ForceField<N, V>],
+  /// Rigid bodies interacting with the points through the velocity-level solver.
+  rigid_bodies: ~[@mut RigidBodyProxy<N, V>],
+  rigid_attachments: ~[RigidAttachment<V>],
+  triangles:   ~[(uint, uint, uint)],
+  self_collision: Option<SelfCollision<N>>,
+  stepper:     Stepper<N>,
+  priv previous_positions: ~[V],
+  /// Global matrix of the Projective Dynamics solver. Factorized on the first solve, and rebuilt
+  /// when the pins or the timestep change.
+  projective:  Option<ProjectiveSystem<N>>,
+  /// Distance to the colliders at which contacts start being generated.
+  margin:      N
 }
 
-impl<N: DivisionRing + NumCast + Signed + Bounded + Eq + Ord + Clone,
-     V: VectorSpace<N> + Norm<N> + Dot<N> + Clone>
+impl<N: DivisionRing + NumCast + Signed + Orderable + Bounded + Round + Trigonometric + Eq + Ord + Clone,
+     V: VectorSpace<N> + Norm<N> + Dot<N> + Cross<V> + Clone>
     SoftBody<N, V>
 {
   pub fn from_mesh(vbuf:      ~[V],
@@ -39,6 +139,14 @@ impl<N: DivisionRing + NumCast + Signed + Bounded + Eq + Ord + Clone,
                    ids2:      ~[i32],
                    invmasses: ~[N],
                    stiffness: ~[N]) -> SoftBody<N, V>
+  { SoftBody::from_mesh_with_solver(vbuf, ids1, ids2, invmasses, stiffness, PGSSolver) }
+
+  pub fn from_mesh_with_solver(vbuf:      ~[V],
+                               ids1:      ~[i32],
+                               ids2:      ~[i32],
+                               invmasses: ~[N],
+                               stiffness: ~[N],
+                               solver:    SolverKind) -> SoftBody<N, V>
   {
     assert!(vbuf.len() == invmasses.len(),
             "Vertex buffer and mass informations must have the same size.");
@@ -49,9 +157,11 @@ impl<N: DivisionRing + NumCast + Signed + Bounded + Eq + Ord + Clone,
     for (v, m) in vbuf.iter().zip(invmasses.iter())
     {
       points.push(PointMass {
-        invmass:    m.clone(),
-        velocity:   Zero::zero(),
-        position:   v.clone(),
+        invmass:       m.clone(),
+        damping:       Zero::zero(),
+        velocity:      Zero::zero(),
+        position:      v.clone(),
+        last_position: v.clone()
       });
     }
 
@@ -63,40 +173,288 @@ impl<N: DivisionRing + NumCast + Signed + Bounded + Eq + Ord + Clone,
       let v1 = ids1[i];
       let v2 = ids2[i];
       let s  = stiffness[i].clone();
+      let l  = (vbuf[v1] - vbuf[v2]).norm();
 
       constraints.push(ConstraintsGeometry {
         stiffness:   s.clone(),
-        rest_length: (vbuf[v1] - vbuf[v2]).norm(),
+        compliance:  if s.is_zero() { Bounded::max_value() } else { One::one::<N>() / s },
+        rest_length: l.clone(),
+        initial_rest_length: l,
+        yield_strain: Bounded::max_value(),
+        creep:        Zero::zero(),
+        max_plastic_strain: Zero::zero(),
+        damping:     Zero::zero(),
         impulse:     Zero::zero(),
+        break_strain:  Bounded::max_value(),
+        break_impulse: Bounded::max_value(),
+        strain_limit:  Bounded::max_value(),
         rb1:         v1 as uint,
         rb2:         v2 as uint
       });
     }
 
+    let stepper = Stepper::new(NumCast::from::<N, float>(0.016), 1);
+
     SoftBody {
+      solver:      solver,
+      iterations:  50,
+      strain_iterations: 20,
+      strain_tolerance:  NumCast::from::<N, float>(1.0e-3),
       points:      points,
       constraints: constraints,
+      bendings:    ~[],
+      membrane:    ~[],
+      volumes:     ~[],
+      inflation:   None,
+      shape_groups: ~[],
+      pins:        ~[],
+      anchors:     ~[],
+      colliders:   ~[],
+      aerodynamics: None,
+      force_fields: ~[],
+      rigid_bodies: ~[],
+      rigid_attachments: ~[],
+      triangles:   ~[],
+      self_collision: None,
+      stepper:     stepper,
+      previous_positions: ~[],
+      projective:  None,
+      margin:      Zero::zero(),
+      time:        Zero::zero(),
+      drag:        Zero::zero(),
       ext_forces:  Zero::zero()
     }
   }
 
+  /// Adds dihedral angle constraints on pairs of adjacent triangles (see `Mesh::bending_pairs`).
+  /// Their rest angles are the current ones.
+  pub fn add_bending_constraints(&mut self, pairs: &[(uint, uint, uint, uint)], stiffness: &[N])
+  {
+    assert!(pairs.len() == stiffness.len(),
+            "Bending pairs and stiffness informations must have the same size.");
+
+    let positions = self.positions();
+
+    for (pair, s) in pairs.iter().zip(stiffness.iter())
+    { self.bendings.push(BendingConstraint::new(*pair, positions, s.clone())) }
+  }
+
+  pub fn pin(&mut self, i: uint)
+  { self.pin_point(i, None) }
+
+  /// Pins a vertex and makes it follow `target(t)`.
+  pub fn pin_with_target(&mut self, i: uint, target: @fn(N) -> V)
+  { self.pin_point(i, Some(target)) }
+
+  pub fn pin_all(&mut self, ids: &[uint])
+  {
+    for i in ids.iter()
+    { self.pin(*i) }
+  }
+
+  /// Pins every vertex whose current position satisfies `pred`. Returns the pinned vertices.
+  pub fn pin_where(&mut self, pred: &fn(&V) -> bool) -> ~[uint]
+  {
+    let mut ids = ~[];
+
+    for (i, p) in self.points.iter().enumerate()
+    {
+      if pred(&p.position)
+      { ids.push(i) }
+    }
+
+    self.pin_all(ids);
+
+    ids
+  }
+
+  fn pin_point(&mut self, i: uint, target: Option<@fn(N) -> V>)
+  {
+    match self.pins.iter().position_(|p| p.id == i)
+    {
+      Some(pid) => self.pins[pid].target = target,
+      None      =>
+      {
+        self.pins.push(Pin::new(i, self.points[i].invmass.clone(), target));
+        self.points[i].invmass  = Zero::zero();
+        self.points[i].velocity = Zero::zero();
+        self.projective         = None;
+      }
+    }
+  }
+
+  pub fn unpin(&mut self, i: uint)
+  {
+    match self.pins.iter().position_(|p| p.id == i)
+    {
+      Some(pid) =>
+      {
+        let pin = self.pins.swap_remove(pid);
+
+        self.points[i].invmass = pin.invmass;
+        self.projective        = None;
+      },
+      None => { }
+    }
+  }
+
+  pub fn unpin_all(&mut self)
+  {
+    while !self.pins.is_empty()
+    {
+      let pin = self.pins.pop();
+
+      self.points[pin.id].invmass = pin.invmass;
+    }
+
+    self.projective = None;
+  }
+
+  pub fn is_pinned(&self, i: uint) -> bool
+  { self.pins.iter().any_(|p| p.id == i) }
+
+  /// Adds an obstacle and returns its index.
+  pub fn add_collider(&mut self, collider: Collider<N, V>) -> uint
+  {
+    self.colliders.push(collider);
+    self.colliders.len() - 1
+  }
+
+  pub fn remove_collider(&mut self, i: uint) -> Collider<N, V>
+  { self.colliders.remove(i) }
+
+  /// Adds a force field and returns its index.
+  pub fn add_force_field(&mut self, field: @ForceField<N, V>) -> uint
+  {
+    self.force_fields.push(field);
+    self.force_fields.len() - 1
+  }
+
+  pub fn remove_force_field(&mut self, i: uint) -> @ForceField<N, V>
+  { self.force_fields.remove(i) }
+
+  /// Sets the triangles of the surface, as given by `Mesh::ibuff`.
+  pub fn set_triangles(&mut self, ibuff: &[(u32, u32, u32)])
+  { self.triangles = ibuff.iter().transform(|&(a, b, c)| (a as uint, b as uint, c as uint)).collect() }
+
+  /// Keeps non-adjacent parts of the surface at least `thickness` apart. The triangles must have
+  /// been set.
+  pub fn enable_self_collision(&mut self, thickness: N)
+  {
+    assert!(!self.triangles.is_empty(), "Self-collisions require the triangles of the surface.");
+
+    // cells must be large enough for a triangle not to span too many of them
+    let mut mean_length = Zero::zero::<N>();
+
+    for c in self.constraints.iter()
+    { mean_length = mean_length + c.rest_length }
+
+    if !self.constraints.is_empty()
+    { mean_length = mean_length / NumCast::from::<N, uint>(self.constraints.len()) }
+
+    let two: N    = NumCast::from::<N, float>(2.0);
+    let cell_size = mean_length.max(&(thickness * two));
+
+    self.self_collision = Some(SelfCollision::new(self.triangles, thickness, cell_size));
+  }
+
+  pub fn disable_self_collision(&mut self)
+  { self.self_collision = None }
+
+  pub fn positions(&self) -> ~[V]
+  { self.points.iter().transform(|p| p.position.clone()).collect() }
+
+  /// Positions to render: interpolated between the last two steps of `step` if the stepper says
+  /// so, the current positions otherwise.
+  pub fn interpolated_positions(&self) -> ~[V]
+  {
+    if !self.stepper.interpolate || self.previous_positions.len() != self.points.len()
+    { return self.positions() }
+
+    let alpha = self.stepper.alpha();
+
+    self.points.iter().zip(self.previous_positions.iter()).transform(|(p, prev)| {
+      *prev + (p.position - *prev).scalar_mul(&alpha)
+    }).collect()
+  }
+
+  /// Forces applied on each point, in addition to the uniform acceleration given to `integrate`.
+  /// `fields` are applied in addition to the force fields of the body.
+  pub fn point_forces(&self, fields: &[@ForceField<N, V>]) -> ~[V]
+  {
+    let mut forces = vec::from_elem(self.points.len(), Zero::zero::<V>());
+
+    for e in self.membrane.iter()
+    { e.accumulate_forces(self.points, forces) }
+
+    for aero in self.aerodynamics.iter()
+    {
+      assert!(!self.triangles.is_empty(), "Aerodynamic forces require the triangles of the surface.");
+
+      aero.accumulate_forces(self.points, self.triangles, &self.time, forces);
+    }
+
+    for field in self.force_fields.iter().chain_(fields.iter())
+    {
+      for (i, (p, f)) in self.points.iter().zip(forces.mut_iter()).enumerate()
+      {
+        if !p.invmass.is_zero()
+        { *f = *f + field.force(i, &p.position, &p.velocity, &p.invmass, &self.time) }
+      }
+    }
+
+    forces
+  }
+
   pub fn integrate(&mut self, dt: &N, fext: &V)
+  { self.integrate_with_fields(dt, fext, []) }
+
+  /// Integrates with the force fields `fields` applied in addition to those of the body.
+  pub fn integrate_with_fields(&mut self, dt: &N, fext: &V, fields: &[@ForceField<N, V>])
   {
+    let forces = self.point_forces(fields);
+
     self.ext_forces = fext.clone();
+    self.time       = self.time + *dt;
 
-    for p in self.points.mut_iter()
+    for (p, f) in self.points.mut_iter().zip(forces.iter())
     {
+      p.last_position = p.position.clone();
+
       if !p.invmass.is_zero()
       {
-        p.velocity = p.velocity + fext.scalar_mul(dt);;
+        let decay = (One::one::<N>() - (self.drag + p.damping) * *dt).max(&Zero::zero());
+        let dv    = fext.scalar_mul(dt) + f.scalar_mul(&(p.invmass * *dt));
+
+        p.velocity = (p.velocity + dv).scalar_mul(&decay);
         p.position = p.position + p.velocity.scalar_mul(dt);
       }
     }
+
+    for c in self.colliders.mut_iter()
+    { c.update(self.time.clone(), dt) }
+
+    // kinematic vertices
+    for pin in self.pins.iter()
+    {
+      match pin.target
+      {
+        Some(target) =>
+        {
+          let p   = &mut self.points[pin.id];
+          let pos = target(self.time.clone());
+
+          p.velocity = (pos - p.position).scalar_div(dt);
+          p.position = pos;
+        },
+        None => { }
+      }
+    }
   }
 
   pub fn collect_constraints(&self,
                              dt:          N,
-                             out:         &mut ~[VelocityConstraint<V, Vec1<N>, N>],
+                             out:         &mut ~[VelocityConstraint<V, V, N>],
                              first_order: bool)
   {
     for c in self.constraints.iter()
@@ -113,11 +471,16 @@ impl<N: DivisionRing + NumCast + Signed + Bounded + Eq + Ord + Clone,
       {
         dvel = dvel + dt * ((length - c.rest_length) * c.stiffness);
 
+        // gravity does not act on pinned vertices, but those following a target have a velocity.
         if !m2.is_zero()
         { dvel = dvel - (self.points[c.rb2].velocity + self.ext_forces.scalar_mul(&dt)).dot(&normal) }
+        else
+        { dvel = dvel - self.points[c.rb2].velocity.dot(&normal) }
 
         if !m1.is_zero()
         { dvel = dvel + (self.points[c.rb1].velocity + self.ext_forces.scalar_mul(&dt)).dot(&normal) }
+        else
+        { dvel = dvel + self.points[c.rb1].velocity.dot(&normal) }
       }
       else
       {
@@ -155,29 +518,322 @@ impl<N: DivisionRing + NumCast + Signed + Bounded + Eq + Ord + Clone,
       }
     }
   }
+
+  /// Unilateral contacts between the points and the colliders. The friction constraints refer to
+  /// the contacts through their index in `out`.
+  pub fn collect_contacts(&self,
+                          dt:       N,
+                          out:      &mut ~[VelocityConstraint<V, V, N>],
+                          friction: &mut ~[VelocityConstraint<V, V, N>])
+  { self.collect_collider_contacts(self.colliders, dt, out, friction) }
+
+  /// Same as `collect_contacts`, with colliders which do not belong to the body.
+  pub fn collect_collider_contacts(&self,
+                                   colliders: &[Collider<N, V>],
+                                   dt:        N,
+                                   out:       &mut ~[VelocityConstraint<V, V, N>],
+                                   friction:  &mut ~[VelocityConstraint<V, V, N>])
+  {
+    // fraction of the penetration corrected at each step
+    let erp: N = NumCast::from::<N, float>(0.4);
+
+    for collider in colliders.iter()
+    {
+      for (i, p) in self.points.iter().enumerate()
+      {
+        if p.invmass.is_zero()
+        { loop }
+
+        match collider.contact(&p.position, &self.margin)
+        {
+          Some((n, dist)) =>
+          {
+            // the velocity the point will have at the next integration step
+            let vrel = p.velocity + self.ext_forces.scalar_mul(&dt) - collider.velocity;
+            let vn   = vrel.dot(&n);
+
+            // penetrations are corrected progressively, gaps may be closed in one step
+            let bias = if dist < Zero::zero() { -dist * erp / dt } else { -dist / dt };
+
+            // a positive impulse pushes the point along `n`
+            let normal = -n;
+
+            out.push(self.point_constraint(i, normal, bias - vn, Zero::zero(), Bounded::max_value()));
+
+            let mut tangent = vrel - n.scalar_mul(&vn);
+            let     vt      = tangent.normalize();
+
+            if !collider.friction.is_zero() && vt > Zero::zero()
+            {
+              let mut f = self.point_constraint(i, tangent, vt, Zero::zero(), Zero::zero());
+
+              f.friction_limit_id = out.len() - 1;
+              f.friction_coeff    = collider.friction.clone();
+
+              friction.push(f);
+            }
+          },
+          None => { }
+        }
+      }
+    }
+  }
+
+  // Constraint between the point `i` and the static world.
+  fn point_constraint(&self, i: uint, normal: V, objective: N, lobound: N, hibound: N)
+                      -> VelocityConstraint<V, V, N>
+  { velocity_constraint(self.points, i as int, -1, normal, objective, lobound, hibound) }
+}
+
+/// Constraint between the points `i1` and `i2` (`-1` for the static world): a positive impulse
+/// pushes `i1` along `-normal` and `i2` along `normal`. Pinned points are seen as static.
+pub fn velocity_constraint<N: DivisionRing + Clone, V: VectorSpace<N> + Clone>(
+                           points:    &[PointMass<N, V>],
+                           i1:        int,
+                           i2:        int,
+                           normal:    V,
+                           objective: N,
+                           lobound:   N,
+                           hibound:   N) -> VelocityConstraint<V, V, N>
+{
+  let m1 = if i1 < 0 { Zero::zero() } else { points[i1].invmass.clone() };
+  let m2 = if i2 < 0 { Zero::zero() } else { points[i2].invmass.clone() };
+
+  VelocityConstraint {
+    weighted_normal1:   normal.scalar_mul(&m1),
+    weighted_normal2:   normal.scalar_mul(&m2),
+
+    rot_axis1:          Zero::zero(),
+    weighted_rot_axis1: Zero::zero(),
+
+    rot_axis2:          Zero::zero(),
+    weighted_rot_axis2: Zero::zero(),
+
+    inv_projected_mass: One::one::<N>() / (m1 + m2),
+
+    impulse:            Zero::zero(),
+    unit_impulse:       Zero::zero(),
+    lobound:            lobound,
+    hibound:            hibound,
+    objective:          objective,
+    id1:                if m1.is_zero() { -1 } else { i1 },
+    id2:                if m2.is_zero() { -1 } else { i2 },
+
+    normal:             normal,
+    friction_limit_id:  0,
+    friction_coeff:     Zero::zero(),
+  }
+}
+
+/// Projected Gauss-Seidel resolution of `constraints` and `friction`, one iteration at a time,
+/// each iteration being followed by `sweep`: a Gauss-Seidel sweep over the constraints involving
+/// more than two points, which updates the velocities of `points` in place. The velocity changes
+/// of the sweeps enter the objectives of the next iteration, so that all the constraints are
+/// solved together.
+///
+/// The points come first in the equations, followed by `nrigids` rigid bodies. The velocities
+/// of the points are updated; the linear and angular velocity changes of the rigid bodies are
+/// returned.
+pub fn gauss_seidel_solve<N: DivisionRing + Orderable + NumCast + Signed + Bounded + Clone,
+                          V: VectorSpace<N> + Dot<N> + Norm<N> + Cross<V> + Clone>(
+                          points:      &mut [PointMass<N, V>],
+                          constraints: &mut [VelocityConstraint<V, V, N>],
+                          friction:    &mut [VelocityConstraint<V, V, N>],
+                          nrigids:     uint,
+                          iterations:  uint,
+                          sweep:       &fn(&mut [PointMass<N, V>])) -> ~[(V, V)]
+{
+  let npoints = points.len();
+  let initial: ~[V]    = points.iter().transform(|p| p.velocity.clone()).collect();
+  let objectives: ~[N] = constraints.iter().chain_(friction.iter()).transform(|c| c.objective.clone()).collect();
+
+  // velocity changes of the points due to the sweeps
+  let mut dvs = vec::from_elem(npoints, Zero::zero::<V>());
+  let mut res = ~[];
+
+  // objective of a two-body constraint, given the velocity changes of the sweeps
+  let objective = |c: &VelocityConstraint<V, V, N>, o: &N, dvs: &[V]| -> N {
+    let mut o = o.clone();
+
+    if c.id1 >= 0 && (c.id1 as uint) < npoints
+    { o = o + c.normal.dot(&dvs[c.id1]) }
+
+    if c.id2 >= 0 && (c.id2 as uint) < npoints
+    { o = o - c.normal.dot(&dvs[c.id2]) }
+
+    o
+  };
+
+  for _ in range(0u, iterations)
+  {
+    let nconstraints = constraints.len();
+
+    for (c, o) in constraints.mut_iter().zip(objectives.iter())
+    { c.objective = objective(c, o, dvs) }
+
+    for (f, o) in friction.mut_iter().zip(objectives.slice_from(nconstraints).iter())
+    { f.objective = objective(f, o, dvs) }
+
+    // the impulses accumulated by the previous iterations are applied first
+    res = projected_gauss_seidel_solve(constraints, friction, npoints + nrigids, 1, false);
+
+    for (i, p) in points.mut_iter().enumerate()
+    { p.velocity = initial[i] + res[i].lv + dvs[i] }
+
+    sweep(points);
+
+    for (i, p) in points.iter().enumerate()
+    { dvs[i] = p.velocity - initial[i] - res[i].lv }
+  }
+
+  range(npoints, npoints + nrigids).transform(|i| {
+    if res.is_empty() { (Zero::zero(), Zero::zero()) } else { (res[i].lv.clone(), res[i].av.clone()) }
+  }).collect()
 }
 
-impl<V: VectorSpace<N> + Dot<N> + Norm<N> + Clone + ToStr,
-     N:  DivisionRing + Orderable + NumCast + Signed + Bounded + Ord + ToStr + Eq + Clone>
+impl<V: VectorSpace<N> + Dot<N> + Norm<N> + Cross<V> + Indexable<uint, N> + Dim + Clone + ToStr,
+     N:  DivisionRing + Orderable + NumCast + Signed + Bounded + Round + Trigonometric + Ord + ToStr + Eq + Clone>
      SoftBody<N, V>
 {
   pub fn solve(&mut self, dt: N)
   {
-    let mut constraints = ~[];
+    match self.solver
+    {
+      PGSSolver                => self.solve_pgs(dt),
+      XPBDSolver               => self.solve_xpbd(dt),
+      ImplicitEulerSolver      => self.solve_implicit(dt),
+      ProjectiveDynamicsSolver => self.solve_projective(dt)
+    }
+  }
+
+  pub fn solve_pgs(&mut self, dt: N)
+  { self.solve_velocity_constraints(dt, true) }
+
+  /// Resolves the contacts, the rigid body couplings, the self-collisions, the bending and volume
+  /// constraints, and the springs if `springs` is set, together with projected Gauss-Seidel (see
+  /// `gauss_seidel_solve`).
+  pub fn solve_velocity_constraints(&mut self, dt: N, springs: bool)
+  {
+    let fext_dt           = self.ext_forces.scalar_mul(&dt);
+    let mut constraints   = ~[];
+    let mut friction      = ~[];
+    let mut self_contacts = ~[];
+    let mut projections   = ~[];
 
     // second order resolution
-    self.collect_constraints(dt.clone(), &mut constraints, false);
-  
-    let res = projected_gauss_seidel_solve(constraints,
-                                           [],
-                                           self.points.len(),
-                                           50,
-                                           false);
-
-    for (i, p) in self.points.mut_iter().enumerate()
-    { p.velocity = p.velocity + res[i].lv }
-
-    for (i, c) in constraints.iter().enumerate()
-    { self.constraints[i].impulse = c.impulse.clone() }
+    if springs
+    { self.collect_constraints(dt.clone(), &mut constraints, false) }
+
+    self.collect_contacts(dt.clone(), &mut constraints, &mut friction);
+    self.collect_rigid_constraints(dt.clone(), &mut constraints, &mut friction);
+
+    match self.self_collision
+    {
+      Some(ref sc) => sc.collect_contacts(self.points, self.triangles, &dt, &mut self_contacts),
+      None => { }
+    }
+
+    self.collect_projections(&mut projections);
+
+    let res = do gauss_seidel_solve(self.points, constraints, friction, self.rigid_bodies.len(),
+                                    self.iterations) |points| {
+      projection::sweep_velocities(points, projections, &dt, &fext_dt);
+      self_collision::solve_contacts(points, self_contacts, &fext_dt)
+    };
+
+    for (body, &(ref lv, ref av)) in self.rigid_bodies.iter().zip(res.iter())
+    { body.apply_velocity_change(lv, av) }
+
+    // contacts are not warm-started
+    if springs
+    {
+      for i in range(0u, self.constraints.len())
+      { self.constraints[i].impulse = constraints[i].impulse.clone() }
+    }
+  }
+
+  /// Bending and volume constraints, to be resolved at the velocity level by
+  /// `projection::sweep_velocities`.
+  pub fn collect_projections(&self, out: &mut ~[VelocityProjection<N, V>])
+  {
+    self.collect_bending_projections(out);
+    self.collect_volume_projections(out);
+  }
+
+  pub fn collect_bending_projections(&self, out: &mut ~[VelocityProjection<N, V>])
+  {
+    for b in self.bendings.iter()
+    {
+      if b.stiffness.is_zero()
+      { loop }
+
+      let (e0, e1, o1, o2) = b.ids;
+
+      match dihedral_angle(&self.points[e0].position, &self.points[e1].position,
+                           &self.points[o1].position, &self.points[o2].position)
+      {
+        Some((angle, grads)) => out.push(VelocityProjection {
+          ids:       b.id_list(),
+          grads:     grads,
+          value:     angle - b.rest_angle,
+          stiffness: b.stiffness.clone()
+        }),
+        None => { }
+      }
+    }
+  }
+
+  pub fn solve_bending_positions(&mut self, dt2: &N)
+  {
+    for b in self.bendings.mut_iter()
+    {
+      if b.stiffness.is_zero()
+      { loop }
+
+      let (e0, e1, o1, o2) = b.ids;
+
+      match dihedral_angle(&self.points[e0].position, &self.points[e1].position,
+                           &self.points[o1].position, &self.points[o2].position)
+      {
+        Some((angle, grads)) =>
+          projection::project_positions(self.points, b.id_list(), grads, &(angle - b.rest_angle),
+                                        &(b.compliance / *dt2), &mut b.impulse),
+        None => { }
+      }
+    }
+  }
+
+  /// Advances the simulation by a wall-clock `delta`, running as many fixed steps as the stepper
+  /// accumulated. Returns the number of steps run.
+  pub fn step(&mut self, delta: N, fext: &V) -> uint
+  {
+    let nsteps = self.stepper.advance(delta);
+    let dt     = self.stepper.substep_dt();
+
+    for _ in range(0u, nsteps)
+    {
+      self.previous_positions = self.positions();
+
+      for _ in range(0u, self.stepper.substeps)
+      {
+        self.integrate(&dt, fext);
+        self.solve(dt.clone());
+        self.finish_substep(&dt);
+      }
+    }
+
+    nsteps
+  }
+
+  /// Strain limiting, anchors, shape matching, damping, plasticity and tearing, run after the
+  /// resolution of each substep.
+  pub fn finish_substep(&mut self, dt: &N)
+  {
+    self.limit_strain(dt);
+    self.project_anchors(dt);
+    self.project_shape_matching(dt);
+    self.damp_constraints(dt);
+    self.update_plasticity(dt);
+    self.tear();
   }
 }
@@ -1,11 +1,18 @@
 use std::vec;
 use std::num::Zero;
+use std::hashmap::HashSet;
 use OpenCL::hl::*;
 use OpenCL::vector::Vector;
 use nalgebra::traits::norm::Norm;
+use nalgebra::traits::dot::Dot;
 use nalgebra::traits::scalar_op::ScalarMul;
-
+use nalgebra::vec::Vec3;
 use rs2cl::nalgebra2cl::CLVec3f64;
+use pin::Pin;
+use force_field::ForceField;
+use bending::BendingConstraint;
+use graph;
+use graph::FanSplit;
 
 pub struct ConstraintsGeometry
 {
@@ -16,14 +23,36 @@ pub struct ConstraintsGeometry
   rb2:         uint
 }
 
+// Dihedral bending constraints on the device, sorted by colour.
+struct BendingBuffers
+{
+  e0s:    Vector<i32>,
+  e1s:    Vector<i32>,
+  o1s:    Vector<i32>,
+  o2s:    Vector<i32>,
+  stiffs: Vector<f64>,
+  rests:  Vector<f64>
+}
+
 pub struct SoftBodyGpu
 {
   ext_forces:  CLVec3f64,
+  time:        f64,
+  /// Global linear drag: rate at which the velocity of every point decays, per second.
+  drag:        f64,
+  /// Per-point external forces, evaluated on the host and applied at integration.
+  force_fields: ~[@ForceField<f64, Vec3<f64>>],
+  pins:        ~[Pin<f64, CLVec3f64>],
 
   // point masses
   positions:  ~[CLVec3f64],
   velocities: ~[CLVec3f64],
   masses:     ~[f64],
+  dampings:   ~[f64],
+  cl_damp:    Vector<f64>,
+  // accelerations due to the force fields
+  accelerations: ~[CLVec3f64],
+  cl_acc:        Vector<CLVec3f64>,
 
   // constants for the solver
   real_id1s:   ~[i32],
@@ -38,6 +67,7 @@ pub struct SoftBodyGpu
   cl_batch_sizes: Vector<i32>,
 
   num_colors:     uint,
+  colors:         ~[i32],
   colors_sizes:   ~[i32],
 
   pmasses:   ~[f64],
@@ -54,6 +84,29 @@ pub struct SoftBodyGpu
   stiffs:   ~[f64],
   cl_stiff: Vector<f64>,
 
+  // damping coefficient of the constraints (see `set_damping`)
+  constraint_dampings: ~[f64],
+
+  // plasticity (see `set_plasticity`)
+  initial_rests:       ~[f64],
+  yield_strains:       ~[f64],
+  creeps:              ~[f64],
+  max_plastic_strains: ~[f64],
+
+  // break thresholds (see `tear`)
+  break_strains:  ~[f64],
+  break_impulses: ~[f64],
+
+  // strain limiting (see `limit_strain_gpu`)
+  strain_limits:     ~[f64],
+  cl_strain_limit:   Vector<f64>,
+  violations:        ~[f64],
+  cl_violation:      Vector<f64>,
+  /// Maximum number of iterations of the strain limiting pass.
+  strain_iterations: uint,
+  /// Strain excess, relative to the rest length, under which the strain limiting pass stops.
+  strain_tolerance:  f64,
+
   // cl buffers
   normals:  ~[CLVec3f64],
   cl_nor:   Vector<CLVec3f64>,
@@ -69,6 +122,15 @@ pub struct SoftBodyGpu
   cl_pos:     Vector<CLVec3f64>,
   cl_vel:     Vector<CLVec3f64>,
 
+  // dihedral bending constraints, and the first constraint and number of constraints of each
+  // colour on the device
+  bendings:       ~[BendingConstraint<f64>],
+  bending_starts: ~[i32],
+  bending_sizes:  ~[i32],
+  cl_bendings:    Option<BendingBuffers>,
+
+  /// Triangles of the surface (see `set_triangles`). Tearing changes them.
+  triangles: ~[(uint, uint, uint)]
 }
 
 impl SoftBodyGpu
@@ -82,7 +144,6 @@ impl SoftBodyGpu
                    batch_sizes:  ~[i32],
                    invmasses:    ~[f64],
                    stiffness:    ~[f64],
-                   solver:       &Kernel,
                    ctx:          @ComputeContext) -> SoftBodyGpu
   {
     println("oid1s: " + oid1s.to_str());
@@ -128,17 +189,33 @@ impl SoftBodyGpu
     let hig        = vec::from_elem(rests.len(), Bounded::max_value::<f64>());
     let vels       = vec::from_elem(invmasses.len(), Zero::zero());
 
-    let res = SoftBodyGpu {
+    let nconstraints = rests.len();
+
+    SoftBodyGpu {
       num_colors:     colors.len(),
-      colors_sizes:   colors_sizes,
       cl_colors:      Vector::from_vec(ctx, colors),
+      colors:         colors,
+      colors_sizes:   colors_sizes,
       cl_batches:     Vector::from_vec(ctx, batches),
       cl_batch_sizes: Vector::from_vec(ctx, batch_sizes),
       ext_forces:  Zero::zero(),
+      time:        0.0,
+      drag:        0.0,
+      force_fields: ~[],
+      pins:        ~[],
+      bendings:       ~[],
+      bending_starts: ~[],
+      bending_sizes:  ~[],
+      cl_bendings:    None,
+      triangles:      ~[],
       cl_pos:      Vector::from_vec(ctx, vbuf),
       positions:   vbuf,
+      cl_acc:        Vector::from_vec(ctx, vels),
+      accelerations: vels.clone(),
       cl_vel:      Vector::from_vec(ctx, vels),
       velocities:  vels,
+      cl_damp:     Vector::from_vec(ctx, vec::from_elem(invmasses.len(), 0.0f64)),
+      dampings:    vec::from_elem(invmasses.len(), 0.0),
       cl_mas:      Vector::from_vec(ctx, invmasses),
       masses:      invmasses,
       cl_real_id1: Vector::from_vec(ctx, id1s),
@@ -162,25 +239,237 @@ impl SoftBodyGpu
       cl_hig:      Vector::from_vec(ctx, hig),
       hig:         hig,
       cl_rest:     Vector::from_vec(ctx, rests),
+      initial_rests: rests.clone(),
       rests:       rests,
-    };
+      constraint_dampings: vec::from_elem(nconstraints, 0.0),
+      yield_strains:       vec::from_elem(nconstraints, Bounded::max_value()),
+      creeps:              vec::from_elem(nconstraints, 0.0),
+      max_plastic_strains: vec::from_elem(nconstraints, 0.0),
+      break_strains:  vec::from_elem(nconstraints, Bounded::max_value()),
+      break_impulses: vec::from_elem(nconstraints, Bounded::max_value()),
+      strain_limits:     vec::from_elem(nconstraints, Bounded::max_value()),
+      cl_strain_limit:   Vector::from_vec(ctx, vec::from_elem(nconstraints, Bounded::max_value::<f64>())),
+      violations:        vec::from_elem(nconstraints, 0.0),
+      cl_violation:      Vector::from_vec(ctx, vec::from_elem(nconstraints, 0.0f64)),
+      strain_iterations: 20,
+      strain_tolerance:  1.0e-3,
+    }
+  }
+
+  /// Adds dihedral angle constraints on pairs of adjacent triangles (see `Mesh::bending_pairs`).
+  /// Their rest angles are the current ones.
+  pub fn add_bending_constraints(&mut self,
+                                 pairs:     &[(uint, uint, uint, uint)],
+                                 stiffness: &[f64],
+                                 ctx:       @ComputeContext)
+  {
+    assert!(pairs.len() == stiffness.len(),
+            "Bending pairs and stiffness informations must have the same size.");
+
+    let positions: ~[Vec3<f64>] = self.positions.iter().transform(|p| p.val).collect();
+
+    for (pair, s) in pairs.iter().zip(stiffness.iter())
+    { self.bendings.push(BendingConstraint::new(*pair, positions, *s)) }
+
+    self.upload_bendings(ctx);
+  }
+
+  // Colours the bending constraints and (re)creates their buffers on the device.
+  fn upload_bendings(&mut self, ctx: @ComputeContext)
+  {
+    self.bending_starts = ~[];
+    self.bending_sizes  = ~[];
+
+    if self.bendings.is_empty()
+    {
+      self.cl_bendings = None;
+      return
+    }
+
+    let all_pairs: ~[(uint, uint, uint, uint)] = self.bendings.iter().transform(|b| b.ids).collect();
+
+    // the constraints are laid out one colour after the other on the device
+    let mut e0s    = ~[];
+    let mut e1s    = ~[];
+    let mut o1s    = ~[];
+    let mut o2s    = ~[];
+    let mut stiffs = ~[];
+    let mut rests  = ~[];
+
+    for group in graph::color_bending_pairs(all_pairs).iter()
+    {
+      self.bending_starts.push(e0s.len() as i32);
+      self.bending_sizes.push(group.len() as i32);
+
+      for bid in group.iter()
+      {
+        let b                = &self.bendings[*bid];
+        let (e0, e1, o1, o2) = b.ids;
+
+        e0s.push(e0 as i32);
+        e1s.push(e1 as i32);
+        o1s.push(o1 as i32);
+        o2s.push(o2 as i32);
+        stiffs.push(b.stiffness);
+        rests.push(b.rest_angle);
+      }
+    }
+
+    self.cl_bendings = Some(BendingBuffers {
+      e0s:    Vector::from_vec(ctx, e0s),
+      e1s:    Vector::from_vec(ctx, e1s),
+      o1s:    Vector::from_vec(ctx, o1s),
+      o2s:    Vector::from_vec(ctx, o2s),
+      stiffs: Vector::from_vec(ctx, stiffs),
+      rests:  Vector::from_vec(ctx, rests)
+    });
+  }
+
+  /// Sets the triangles of the surface, as given by `Mesh::ibuff`. Required for `tear` to split
+  /// the vertices.
+  pub fn set_triangles(&mut self, ibuff: &[(u32, u32, u32)])
+  { self.triangles = ibuff.iter().transform(|&(a, b, c)| (a as uint, b as uint, c as uint)).collect() }
+
+  /// Sets the damping coefficient of each constraint and the velocity decay rate of each point,
+  /// as given by `MaterialMap::constraint_damping` and `MaterialMap::point_damping`. The
+  /// constraints are in the order given to `from_mesh`.
+  pub fn set_damping(&mut self, constraint_damping: &[f64], point_damping: &[f64])
+  {
+    assert!(constraint_damping.len() == self.constraint_dampings.len(),
+            "Constraints and damping informations must have the same size.");
+    assert!(point_damping.len() == self.dampings.len(),
+            "Points and damping informations must have the same size.");
+
+    self.constraint_dampings = constraint_damping.to_owned();
+    self.dampings            = point_damping.to_owned();
+    self.cl_damp.rewrite(self.dampings);
+  }
+
+  /// Adds a force field and returns its index.
+  pub fn add_force_field(&mut self, field: @ForceField<f64, Vec3<f64>>) -> uint
+  {
+    self.force_fields.push(field);
+    self.force_fields.len() - 1
+  }
 
-    solver.set_arg(0,  &(res.pmasses.len() as i32));
-    solver.set_arg(1,  &res.cl_id1);
-    solver.set_arg(2,  &res.cl_id2);
-    solver.set_arg(3,  &res.cl_nor);
-    solver.set_arg(4,  &res.cl_mas);
-    solver.set_arg(5,  &res.cl_imp);
-    solver.set_arg(6,  &res.cl_low);
-    solver.set_arg(7,  &res.cl_hig);
-    solver.set_arg(8,  &res.cl_obj);
-    solver.set_arg(9, &res.cl_pma);
-    solver.set_arg(11, &res.cl_colors);
-    solver.set_arg(12, &res.cl_batches);
-    solver.set_arg(13, &res.cl_batch_sizes);
+  pub fn remove_force_field(&mut self, i: uint) -> @ForceField<f64, Vec3<f64>>
+  {
+    let res = self.force_fields.remove(i);
+
+    if self.force_fields.is_empty()
+    {
+      for a in self.accelerations.mut_iter()
+      { *a = Zero::zero() }
+
+      self.cl_acc.rewrite(self.accelerations);
+    }
 
     res
   }
+
+  pub fn pin(&mut self, i: uint)
+  {
+    self.pin_point(i, None);
+    self.sync_masses();
+  }
+
+  /// Pins a vertex and makes it follow `target(t)`.
+  pub fn pin_with_target(&mut self, i: uint, target: @fn(f64) -> CLVec3f64)
+  {
+    self.pin_point(i, Some(target));
+    self.sync_masses();
+  }
+
+  pub fn pin_all(&mut self, ids: &[uint])
+  {
+    for i in ids.iter()
+    { self.pin_point(*i, None) }
+
+    self.sync_masses();
+  }
+
+  /// Pins every vertex whose current position satisfies `pred`. Returns the pinned vertices.
+  pub fn pin_where(&mut self, pred: &fn(&CLVec3f64) -> bool) -> ~[uint]
+  {
+    let mut ids = ~[];
+
+    for (i, p) in self.positions.iter().enumerate()
+    {
+      if pred(p)
+      { ids.push(i) }
+    }
+
+    self.pin_all(ids);
+
+    ids
+  }
+
+  // Does not update the device: call `sync_masses` afterwards.
+  fn pin_point(&mut self, i: uint, target: Option<@fn(f64) -> CLVec3f64>)
+  {
+    match self.pins.iter().position_(|p| p.id == i)
+    {
+      Some(pid) => self.pins[pid].target = target,
+      None      =>
+      {
+        self.pins.push(Pin::new(i, self.masses[i], target));
+        self.masses[i]     = 0.0;
+        self.velocities[i] = Zero::zero();
+      }
+    }
+  }
+
+  pub fn unpin(&mut self, i: uint)
+  {
+    match self.pins.iter().position_(|p| p.id == i)
+    {
+      Some(pid) =>
+      {
+        let pin = self.pins.swap_remove(pid);
+
+        self.masses[i] = pin.invmass;
+        self.sync_masses();
+      },
+      None => { }
+    }
+  }
+
+  pub fn unpin_all(&mut self)
+  {
+    while !self.pins.is_empty()
+    {
+      let pin = self.pins.pop();
+
+      self.masses[pin.id] = pin.invmass;
+    }
+
+    self.sync_masses();
+  }
+
+  pub fn is_pinned(&self, i: uint) -> bool
+  { self.pins.iter().any_(|p| p.id == i) }
+
+  // Updates everything depending on the inverse masses on the device.
+  fn sync_masses(&mut self)
+  {
+    let mut cl_id1s = ~[];
+    let mut cl_id2s = ~[];
+
+    for i in range(0u, self.real_id1s.len())
+    {
+      let v1 = self.real_id1s[i];
+      let v2 = self.real_id2s[i];
+
+      cl_id1s.push(if self.masses[v1] == 0.0 { -1 } else { v1 });
+      cl_id2s.push(if self.masses[v2] == 0.0 { -1 } else { v2 });
+      self.pmasses[i] = self.masses[v1] + self.masses[v2];
+    }
+
+    self.cl_mas.rewrite(self.masses);
+    self.cl_pma.rewrite(self.pmasses);
+    self.cl_id1.rewrite(cl_id1s);
+    self.cl_id2.rewrite(cl_id2s);
+  }
 }
 
 impl SoftBodyGpu
@@ -193,6 +482,28 @@ impl SoftBodyGpu
   {
     self.ext_forces = fext.clone();
 
+    if !self.force_fields.is_empty()
+    {
+      for i in range(0u, self.positions.len())
+      {
+        let mut acc = Zero::zero::<Vec3<f64>>();
+
+        if self.masses[i] != 0.0
+        {
+          for field in self.force_fields.iter()
+          {
+            let f = field.force(i, &self.positions[i].val, &self.velocities[i].val, &self.masses[i], &self.time);
+
+            acc = acc + f.scalar_mul(&self.masses[i]);
+          }
+        }
+
+        self.accelerations[i] = CLVec3f64::new(acc);
+      }
+
+      self.cl_acc.rewrite(self.accelerations);
+    }
+
     // FIXME: dont re-create the buffers at each frame!
     self.cl_vel.rewrite(self.velocities);
     self.cl_pos.rewrite(self.positions);
@@ -203,6 +514,9 @@ impl SoftBodyGpu
     integrator.set_arg(3, fext);
     integrator.set_arg(4, dt);
     integrator.set_arg(5, &(self.positions.len() as i32));
+    integrator.set_arg(6, &self.drag);
+    integrator.set_arg(7, &self.cl_damp);
+    integrator.set_arg(8, &self.cl_acc);
 
     let work_group_size = 64;
     let num_work_items  =
@@ -218,9 +532,41 @@ impl SoftBodyGpu
 
     self.cl_vel.to_existing_vec(self.velocities);
     self.cl_pos.to_existing_vec(self.positions);
+
+    // kinematic vertices
+    self.time = self.time + *dt;
+
+    let mut moved = false;
+
+    for pin in self.pins.iter()
+    {
+      match pin.target
+      {
+        Some(target) =>
+        {
+          let pos = target(self.time);
+
+          self.velocities[pin.id] = (pos - self.positions[pin.id]).scalar_mul(&(1.0 / *dt));
+          self.positions[pin.id]  = pos;
+          moved = true;
+        },
+        None => { }
+      }
+    }
+
+    if moved
+    {
+      self.cl_vel.rewrite(self.velocities);
+      self.cl_pos.rewrite(self.positions);
+    }
   }
 
-  pub fn solve_gpu(&mut self, dt: &f64, solver: &Kernel, initializer: &Kernel, ctx: @ComputeContext)
+  pub fn solve_gpu(&mut self,
+                   dt:          &f64,
+                   solver:      &Kernel,
+                   initializer: &Kernel,
+                   bender:      &Kernel,
+                   ctx:         @ComputeContext)
   {
     let mut MJLambdas: ~[CLVec3f64] = vec::from_elem(self.masses.len(), Zero::zero());
 
@@ -305,12 +651,30 @@ impl SoftBodyGpu
      * solver.set_arg(9, &self.cl_obj);
      * solver.set_arg(10, &self.cl_pma);
      */
+    // the point buffers are reallocated when tearing splits vertices
+    solver.set_arg(0,  &(self.pmasses.len() as i32));
+    solver.set_arg(1,  &self.cl_id1);
+    solver.set_arg(2,  &self.cl_id2);
+    solver.set_arg(3,  &self.cl_nor);
+    solver.set_arg(4,  &self.cl_mas);
+    solver.set_arg(5,  &self.cl_imp);
+    solver.set_arg(6,  &self.cl_low);
+    solver.set_arg(7,  &self.cl_hig);
+    solver.set_arg(8,  &self.cl_obj);
+    solver.set_arg(9,  &self.cl_pma);
     solver.set_arg(10, &cl_mjl);
+    solver.set_arg(11, &self.cl_colors);
+    solver.set_arg(12, &self.cl_batches);
+    solver.set_arg(13, &self.cl_batch_sizes);
 
     do 50u.times
     {
       for i in range(0u, self.num_colors)
       {
+        // torn constraints may have emptied a color
+        if self.colors_sizes[i] == 0
+        { loop }
+
         solver.set_arg(14, &(i as i32)); // curr_color
 
         let work_group_size = self.colors_sizes[i] / 184 + 1;
@@ -335,5 +699,451 @@ impl SoftBodyGpu
     { *v = *v + *dv }
 
     self.cl_imp.to_existing_vec(self.impulses);
+
+    self.solve_bending_gpu(dt, bender, ctx);
+  }
+
+  /// Post-processing of a substep, to be called after `solve_gpu`. Same order as
+  /// `SoftBody::finish_substep`: strain limiting, damping of the constraints, plasticity, then
+  /// tearing.
+  pub fn finish_substep_gpu(&mut self, dt: &f64, limiter: &Kernel, ctx: @ComputeContext)
+  {
+    self.limit_strain_gpu(dt, limiter, ctx);
+    self.damp_constraints(*dt);
+    self.update_plasticity(*dt);
+    self.tear(ctx);
+  }
+
+  /// Reduces the relative velocity of the points of each constraint along its direction, as a
+  /// damper of coefficient `constraint_dampings[i]` would during `dt`. Same as
+  /// `SoftBody::damp_constraints`; broken constraints are skipped.
+  pub fn damp_constraints(&mut self, dt: f64)
+  {
+    for c in range(0u, self.num_colors)
+    {
+      let start = self.colors[c] as uint;
+
+      for i in range(start, start + self.colors_sizes[c] as uint)
+      {
+        let v1 = self.real_id1s[i];
+        let v2 = self.real_id2s[i];
+        let w1 = self.masses[v1];
+        let w2 = self.masses[v2];
+
+        if self.constraint_dampings[i] == 0.0 || w1 + w2 == 0.0
+        { loop }
+
+        let mut normal = (self.positions[v1] - self.positions[v2]).val;
+        let     length = normal.normalize();
+
+        if length == 0.0
+        { loop }
+
+        // the damper cannot revert the relative velocity
+        let vn      = (self.velocities[v1].val - self.velocities[v2].val).dot(&normal);
+        let ratio   = (self.constraint_dampings[i] * (w1 + w2) * dt).min(&1.0);
+        let impulse = vn * ratio / (w1 + w2);
+
+        self.velocities[v1] = CLVec3f64::new(self.velocities[v1].val - normal.scalar_mul(&(w1 * impulse)));
+        self.velocities[v2] = CLVec3f64::new(self.velocities[v2].val + normal.scalar_mul(&(w2 * impulse)));
+      }
+    }
+  }
+
+  /// Makes every constraint plastic: when its strain exceeds `yield_strain`, its rest length
+  /// creeps toward its current length at the rate `creep` (fraction of the excess strain absorbed
+  /// per second), up to a relative deviation of `max_plastic_strain`.
+  pub fn set_plasticity(&mut self, yield_strain: f64, creep: f64, max_plastic_strain: f64)
+  {
+    for i in range(0u, self.rests.len())
+    {
+      self.yield_strains[i]       = yield_strain;
+      self.creeps[i]              = creep;
+      self.max_plastic_strains[i] = max_plastic_strain;
+    }
+  }
+
+  /// Updates the rest lengths of the constraints strained beyond their yield threshold, on the
+  /// host and on the device.
+  pub fn update_plasticity(&mut self, dt: f64)
+  {
+    let mut changed = false;
+
+    for i in range(0u, self.rests.len())
+    {
+      if self.creeps[i] == 0.0 || self.rests[i] == 0.0
+      { loop }
+
+      let length = (self.positions[self.real_id1s[i]] - self.positions[self.real_id2s[i]]).norm();
+      let strain = (length - self.rests[i]) / self.rests[i];
+
+      if strain.abs() <= self.yield_strains[i]
+      { loop }
+
+      let excess = strain - self.yield_strains[i] * strain.signum();
+      let rate   = (self.creeps[i] * dt).min(&1.0);
+      let rest   = self.rests[i] * (1.0 + excess * rate);
+      let lo     = self.initial_rests[i] * (1.0 - self.max_plastic_strains[i]).max(&0.0);
+      let hi     = self.initial_rests[i] * (1.0 + self.max_plastic_strains[i]);
+
+      self.rests[i] = rest.max(&lo).min(&hi);
+      changed       = true;
+    }
+
+    if changed
+    { self.cl_rest.rewrite(self.rests) }
+  }
+
+  /// Makes every constraint break when its strain exceeds `strain`, or when the magnitude of its
+  /// impulse exceeds `impulse`.
+  pub fn set_break_thresholds(&mut self, strain: f64, impulse: f64)
+  {
+    for s in self.break_strains.mut_iter()
+    { *s = strain }
+
+    for i in self.break_impulses.mut_iter()
+    { *i = impulse }
+  }
+
+  /// Removes the constraints which exceeded their break thresholds. Returns the number of broken
+  /// constraints.
+  ///
+  /// The colouring is updated in place: a broken constraint is swapped with the last constraint
+  /// of its colour, which is then shrunk by one. Removed constraints stay at the end of their
+  /// colour with null bounds so that they have no effect.
+  ///
+  /// As with `SoftBody::tear`, the bending constraints across a broken edge are removed and, if
+  /// the triangles are known, the vertices whose triangles got disconnected are split. The point
+  /// buffers then grow, and the triangles change. Pinned vertices are never split.
+  pub fn tear(&mut self, ctx: @ComputeContext) -> uint
+  {
+    let unlimited = Bounded::max_value::<f64>();
+
+    if !self.break_strains.iter().any_(|s| *s != unlimited) &&
+       !self.break_impulses.iter().any_(|i| *i != unlimited)
+    { return 0 }
+
+    let mut nbroken   = 0;
+    let mut endpoints = ~[];
+    let mut edges     = HashSet::new();
+
+    for c in range(0u, self.num_colors)
+    {
+      let start = self.colors[c] as uint;
+      let mut i = start;
+
+      while i < start + self.colors_sizes[c] as uint
+      {
+        let v1     = self.real_id1s[i];
+        let v2     = self.real_id2s[i];
+        let length = (self.positions[v1] - self.positions[v2]).norm();
+        let strain = if self.rests[i] == 0.0 { 0.0 } else { (length - self.rests[i]) / self.rests[i] };
+
+        if strain > self.break_strains[i] || self.impulses[i].abs() > self.break_impulses[i]
+        {
+          let last = start + self.colors_sizes[c] as uint - 1;
+
+          edges.insert(if v1 < v2 { (v1 as uint, v2 as uint) } else { (v2 as uint, v1 as uint) });
+
+          for &v in [v1 as uint, v2 as uint].iter()
+          {
+            if !endpoints.contains(&v)
+            { endpoints.push(v) }
+          }
+
+          self.swap_constraints(i, last);
+          self.low[last]            = 0.0;
+          self.hig[last]            = 0.0;
+          self.impulses[last]       = 0.0;
+          self.stiffs[last]         = 0.0;
+          self.creeps[last]         = 0.0;
+          self.constraint_dampings[last] = 0.0;
+          self.break_strains[last]  = Bounded::max_value();
+          self.break_impulses[last] = Bounded::max_value();
+          self.colors_sizes[c]      = self.colors_sizes[c] - 1;
+          nbroken = nbroken + 1;
+
+          // the constraint swapped in is tested at the same index
+        }
+        else
+        { i = i + 1 }
+      }
+    }
+
+    if nbroken == 0
+    { return 0 }
+
+    // bending constraints across a broken edge disappear with it
+    let nbendings = self.bendings.len();
+
+    self.bendings.retain(|b| {
+      let (e0, e1, _, _) = b.ids;
+
+      !edges.contains(&(if e0 < e1 { (e0, e1) } else { (e1, e0) }))
+    });
+
+    let nvertices = self.positions.len();
+
+    if !self.triangles.is_empty()
+    {
+      for v in endpoints.iter()
+      {
+        if !self.is_pinned(*v)
+        { self.split_vertex(*v) }
+      }
+    }
+
+    if self.positions.len() != nvertices
+    {
+      self.cl_pos  = Vector::from_vec(ctx, self.positions.clone());
+      self.cl_vel  = Vector::from_vec(ctx, self.velocities.clone());
+      self.cl_mas  = Vector::from_vec(ctx, self.masses.clone());
+      self.cl_damp = Vector::from_vec(ctx, self.dampings.clone());
+      self.cl_acc  = Vector::from_vec(ctx, self.accelerations.clone());
+    }
+
+    if self.positions.len() != nvertices || self.bendings.len() != nbendings
+    { self.upload_bendings(ctx) }
+
+    self.cl_real_id1.rewrite(self.real_id1s);
+    self.cl_real_id2.rewrite(self.real_id2s);
+    self.cl_rest.rewrite(self.rests);
+    self.cl_stiff.rewrite(self.stiffs);
+    self.cl_low.rewrite(self.low);
+    self.cl_hig.rewrite(self.hig);
+    self.cl_imp.rewrite(self.impulses);
+    self.cl_strain_limit.rewrite(self.strain_limits);
+    self.sync_masses();
+
+    nbroken
+  }
+
+  // Same as `SoftBody::split_vertex`: duplicates `v` for each group of its triangles which are
+  // no longer linked by a constraint along one of their edges. Broken constraints follow the
+  // closest group, they have no effect anyway.
+  fn split_vertex(&mut self, v: uint)
+  {
+    let mut linked = HashSet::new();
+
+    for c in range(0u, self.num_colors)
+    {
+      let start = self.colors[c] as uint;
+
+      for i in range(start, start + self.colors_sizes[c] as uint)
+      {
+        let v1 = self.real_id1s[i] as uint;
+        let v2 = self.real_id2s[i] as uint;
+
+        if v1 == v
+        { linked.insert(v2); }
+        else if v2 == v
+        { linked.insert(v1); }
+      }
+    }
+
+    let split = match FanSplit::new(self.triangles, v, &linked, self.positions.len())
+    {
+      Some(split) => split,
+      None        => return
+    };
+
+    let positions = self.positions.clone();
+    let sqdist    = |a: uint, b: uint| (positions[a] - positions[b]).sqnorm();
+
+    // the mass is shared between the copies
+    let invmass      = self.masses[v] * (split.len() as f64);
+    let position     = self.positions[v].clone();
+    let velocity     = self.velocities[v].clone();
+    let damping      = self.dampings[v];
+    let acceleration = self.accelerations[v].clone();
+
+    self.masses[v] = invmass;
+
+    for _ in range(1u, split.len())
+    {
+      self.positions.push(position.clone());
+      self.velocities.push(velocity.clone());
+      self.masses.push(invmass);
+      self.dampings.push(damping);
+      self.accelerations.push(acceleration.clone());
+    }
+
+    for i in range(0u, self.real_id1s.len())
+    {
+      let v1 = self.real_id1s[i] as uint;
+      let v2 = self.real_id2s[i] as uint;
+
+      if v1 == v
+      { self.real_id1s[i] = split.copy_of(v2, v2, sqdist) as i32 }
+      else if v2 == v
+      { self.real_id2s[i] = split.copy_of(v1, v1, sqdist) as i32 }
+    }
+
+    for b in self.bendings.mut_iter()
+    { b.ids = split.split_bending(b.ids, sqdist) }
+
+    split.split_triangles(self.triangles);
+  }
+
+  /// Keeps the length of every constraint within `[1 - strain, 1 + strain]` times its rest
+  /// length.
+  pub fn set_strain_limit(&mut self, strain: f64)
+  {
+    for s in self.strain_limits.mut_iter()
+    { *s = strain }
+
+    self.cl_strain_limit.rewrite(self.strain_limits);
+  }
+
+  /// Sets the strain limit of each constraint, as given by `MaterialMap::strain_limits`. The
+  /// constraints are in the order given to `from_mesh`.
+  pub fn set_strain_limits(&mut self, strains: &[f64])
+  {
+    assert!(strains.len() == self.strain_limits.len(),
+            "Constraints and strain limit informations must have the same size.");
+
+    self.strain_limits = strains.to_owned();
+    self.cl_strain_limit.rewrite(self.strain_limits);
+  }
+
+  /// Same as `SoftBody::limit_strain`, run on the device one colour after the other. Called by
+  /// `finish_substep_gpu`. Returns the number of sweeps.
+  pub fn limit_strain_gpu(&mut self, dt: &f64, limiter: &Kernel, ctx: @ComputeContext) -> uint
+  {
+    let unlimited = Bounded::max_value::<f64>();
+
+    if !self.strain_limits.iter().any_(|s| *s != unlimited)
+    { return 0 }
+
+    let positions = self.positions.clone();
+
+    self.cl_pos.rewrite(self.positions);
+
+    limiter.set_arg(2, &self.cl_real_id1);
+    limiter.set_arg(3, &self.cl_real_id2);
+    limiter.set_arg(4, &self.cl_pos);
+    limiter.set_arg(5, &self.cl_mas);
+    limiter.set_arg(6, &self.cl_rest);
+    limiter.set_arg(7, &self.cl_strain_limit);
+    limiter.set_arg(8, &self.cl_violation);
+
+    let mut niter = 0;
+
+    while niter < self.strain_iterations
+    {
+      niter = niter + 1;
+
+      for c in range(0u, self.num_colors)
+      {
+        if self.colors_sizes[c] == 0
+        { loop }
+
+        limiter.set_arg(0, &self.colors[c]);
+        limiter.set_arg(1, &self.colors_sizes[c]);
+
+        let work_group_size = 64;
+        let num_work_items  =
+          work_group_size * ((self.colors_sizes[c] as uint + (work_group_size - 1)) / work_group_size);
+
+        enqueue_nd_range_kernel(
+          &ctx.q,
+          limiter,
+          1,
+          0,
+          num_work_items  as int,
+          work_group_size as int);
+      }
+
+      self.cl_violation.to_existing_vec(self.violations);
+
+      let mut worst = 0.0f64;
+
+      for c in range(0u, self.num_colors)
+      {
+        let start = self.colors[c] as uint;
+
+        for i in range(start, start + self.colors_sizes[c] as uint)
+        { worst = worst.max(&self.violations[i].abs()) }
+      }
+
+      if worst <= self.strain_tolerance
+      { break }
+    }
+
+    self.cl_pos.to_existing_vec(self.positions);
+
+    for ((v, p), x) in self.velocities.mut_iter().zip(self.positions.iter()).zip(positions.iter())
+    { *v = *v + (*p - *x).scalar_mul(&(1.0 / *dt)) }
+
+    niter
+  }
+
+  fn swap_constraints(&mut self, i: uint, j: uint)
+  {
+    self.real_id1s.swap(i, j);
+    self.real_id2s.swap(i, j);
+    self.pmasses.swap(i, j);
+    self.impulses.swap(i, j);
+    self.low.swap(i, j);
+    self.hig.swap(i, j);
+    self.rests.swap(i, j);
+    self.initial_rests.swap(i, j);
+    self.yield_strains.swap(i, j);
+    self.creeps.swap(i, j);
+    self.max_plastic_strains.swap(i, j);
+    self.stiffs.swap(i, j);
+    self.constraint_dampings.swap(i, j);
+    self.break_strains.swap(i, j);
+    self.break_impulses.swap(i, j);
+    self.strain_limits.swap(i, j);
+  }
+
+  // Velocity-level resolution of the bending constraints on the device, one launch per colour:
+  // the constraints of a colour share no vertex.
+  fn solve_bending_gpu(&mut self, dt: &f64, bender: &Kernel, ctx: @ComputeContext)
+  {
+    let bendings = match self.cl_bendings
+    {
+      Some(ref b) => b,
+      None        => return
+    };
+
+    self.cl_vel.rewrite(self.velocities);
+    self.cl_pos.rewrite(self.positions);
+
+    bender.set_arg(2,  &bendings.e0s);
+    bender.set_arg(3,  &bendings.e1s);
+    bender.set_arg(4,  &bendings.o1s);
+    bender.set_arg(5,  &bendings.o2s);
+    bender.set_arg(6,  &self.cl_pos);
+    bender.set_arg(7,  &self.cl_vel);
+    bender.set_arg(8,  &self.cl_mas);
+    bender.set_arg(9,  &bendings.stiffs);
+    bender.set_arg(10, &bendings.rests);
+    bender.set_arg(11, &self.ext_forces);
+    bender.set_arg(12, dt);
+
+    do 50u.times
+    {
+      for c in range(0u, self.bending_starts.len())
+      {
+        bender.set_arg(0, &self.bending_starts[c]);
+        bender.set_arg(1, &self.bending_sizes[c]);
+
+        let work_group_size = 64;
+        let num_work_items  =
+          work_group_size * ((self.bending_sizes[c] as uint + (work_group_size - 1)) / work_group_size);
+
+        enqueue_nd_range_kernel(
+          &ctx.q,
+          bender,
+          1,
+          0,
+          num_work_items  as int,
+          work_group_size as int);
+      }
+    }
+
+    self.cl_vel.to_existing_vec(self.velocities);
   }
 }
@@ -0,0 +1,60 @@
+use std::num::Zero;
+use nalgebra::traits::division_ring::DivisionRing;
+
+/// Fixed-timestep accumulator turning wall-clock deltas into simulation steps.
+pub struct Stepper<N>
+{
+  /// Duration of a simulation step.
+  dt:          N,
+  /// Number of integration/resolution passes per step, each of duration `dt / substeps`.
+  substeps:    uint,
+  /// Maximum number of steps run for a single delta: the simulation slows down instead of
+  /// spiraling when it cannot keep up.
+  max_steps:   uint,
+  /// Whether the rendered positions are interpolated between the last two steps.
+  interpolate: bool,
+  priv accumulator: N
+}
+
+impl<N: DivisionRing + NumCast + Ord + Clone> Stepper<N>
+{
+  pub fn new(dt: N, substeps: uint) -> Stepper<N>
+  {
+    assert!(substeps > 0, "The number of substeps cannot be zero.");
+
+    Stepper {
+      dt:          dt,
+      substeps:    substeps,
+      max_steps:   5,
+      interpolate: true,
+      accumulator: Zero::zero()
+    }
+  }
+
+  pub fn substep_dt(&self) -> N
+  { self.dt / NumCast::from::<N, uint>(self.substeps) }
+
+  /// Adds `delta` to the accumulator and returns the number of steps to run.
+  pub fn advance(&mut self, delta: N) -> uint
+  {
+    self.accumulator = self.accumulator + delta;
+
+    let mut nsteps = 0u;
+
+    while self.accumulator >= self.dt && nsteps < self.max_steps
+    {
+      self.accumulator = self.accumulator - self.dt;
+      nsteps = nsteps + 1;
+    }
+
+    // drop the time we could not simulate
+    if self.accumulator >= self.dt
+    { self.accumulator = Zero::zero() }
+
+    nsteps
+  }
+
+  /// Fraction of a step left in the accumulator.
+  pub fn alpha(&self) -> N
+  { self.accumulator / self.dt }
+}
@@ -0,0 +1,92 @@
+use std::num::{Zero, One};
+use nalgebra::traits::division_ring::DivisionRing;
+use nalgebra::traits::norm::Norm;
+use nalgebra::traits::dot::Dot;
+use nalgebra::traits::cross::Cross;
+use nalgebra::traits::vector_space::VectorSpace;
+use soft_body::SoftBody;
+
+impl<N: DivisionRing + NumCast + Signed + Orderable + Bounded + Round + Trigonometric + Eq + Ord + Clone,
+     V: VectorSpace<N> + Norm<N> + Dot<N> + Cross<V> + Clone>
+    SoftBody<N, V>
+{
+  /// Keeps the length of every constraint within `[1 - strain, 1 + strain]` times its rest
+  /// length.
+  pub fn set_strain_limit(&mut self, strain: N)
+  {
+    for c in self.constraints.mut_iter()
+    { c.strain_limit = strain.clone() }
+  }
+
+  /// Sets the strain limit of each constraint, as given by `MaterialMap::strain_limits`.
+  pub fn set_strain_limits(&mut self, strains: &[N])
+  {
+    assert!(strains.len() == self.constraints.len(),
+            "Constraints and strain limit informations must have the same size.");
+
+    for (c, s) in self.constraints.mut_iter().zip(strains.iter())
+    { c.strain_limit = s.clone() }
+  }
+
+  /// Projects the points of the constraints strained beyond their limit until every strain is
+  /// within `strain_tolerance` of its limit, or for at most `strain_iterations` Gauss-Seidel
+  /// sweeps. The displacements are added to the velocities. Returns the number of sweeps.
+  pub fn limit_strain(&mut self, dt: &N) -> uint
+  {
+    let unlimited = Bounded::max_value::<N>();
+
+    if !self.constraints.iter().any_(|c| c.strain_limit != unlimited)
+    { return 0 }
+
+    let _1        = One::one::<N>();
+    let positions = self.positions();
+    let mut niter = 0;
+
+    while niter < self.strain_iterations
+    {
+      let mut worst = Zero::zero::<N>();
+
+      niter = niter + 1;
+
+      for c in self.constraints.iter()
+      {
+        if c.strain_limit == unlimited || c.rest_length.is_zero()
+        { loop }
+
+        let w1 = self.points[c.rb1].invmass.clone();
+        let w2 = self.points[c.rb2].invmass.clone();
+
+        if (w1 + w2).is_zero()
+        { loop }
+
+        let mut normal = self.points[c.rb1].position - self.points[c.rb2].position;
+        let     length = normal.normalize();
+
+        if length.is_zero()
+        { loop }
+
+        let lo     = c.rest_length * (_1 - c.strain_limit).max(&Zero::zero());
+        let hi     = c.rest_length * (_1 + c.strain_limit);
+        let excess = length - length.max(&lo).min(&hi);
+
+        if excess.is_zero()
+        { loop }
+
+        let correction = excess / (w1 + w2);
+
+        worst = worst.max(&(excess.abs() / c.rest_length));
+
+        self.points[c.rb1].position = self.points[c.rb1].position - normal.scalar_mul(&(w1 * correction));
+        self.points[c.rb2].position = self.points[c.rb2].position + normal.scalar_mul(&(w2 * correction));
+      }
+
+      if worst <= self.strain_tolerance
+      { break }
+    }
+
+    for (p, x) in self.points.mut_iter().zip(positions.iter())
+    { p.velocity = p.velocity + (p.position - *x).scalar_div(dt) }
+
+    niter
+  }
+}
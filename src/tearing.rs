@@ -0,0 +1,169 @@
+use std::num::Zero;
+use std::hashmap::HashSet;
+use nalgebra::traits::division_ring::DivisionRing;
+use nalgebra::traits::norm::Norm;
+use nalgebra::traits::dot::Dot;
+use nalgebra::traits::cross::Cross;
+use nalgebra::traits::vector_space::VectorSpace;
+use soft_body::{SoftBody, PointMass, PGSSolver};
+use self_collision::SelfCollision;
+use graph::FanSplit;
+
+impl<N: DivisionRing + NumCast + Signed + Orderable + Bounded + Round + Trigonometric + Eq + Ord + Clone,
+     V: VectorSpace<N> + Norm<N> + Dot<N> + Cross<V> + Clone>
+    SoftBody<N, V>
+{
+  /// Makes every constraint break when its strain `(length - rest_length) / rest_length` exceeds
+  /// `strain`, or when the magnitude of its accumulated impulse exceeds `impulse`. Use
+  /// `Bounded::max_value()` to disable one of the criteria.
+  ///
+  /// Only the PGS solver accumulates the impulses of the constraints: the impulse criterion
+  /// requires `PGSSolver`.
+  pub fn set_break_thresholds(&mut self, strain: N, impulse: N)
+  {
+    assert!(impulse == Bounded::max_value() || self.solver == PGSSolver,
+            "Impulse-based breaking requires the PGS solver.");
+
+    for c in self.constraints.mut_iter()
+    {
+      c.break_strain  = strain.clone();
+      c.break_impulse = impulse.clone();
+    }
+  }
+
+  /// Removes the constraints which exceeded their break thresholds. If the triangles of the
+  /// surface are known, the vertices whose triangles got disconnected are split so that the
+  /// surface actually tears. Returns the number of broken constraints.
+  ///
+  /// Pinned vertices are never split.
+  pub fn tear(&mut self) -> uint
+  {
+    let unlimited = Bounded::max_value::<N>();
+    let impulses  = self.constraints.iter().any_(|c| c.break_impulse != unlimited);
+
+    // the solver may have been changed after `set_break_thresholds`
+    assert!(!impulses || self.solver == PGSSolver, "Impulse-based breaking requires the PGS solver.");
+
+    if !impulses && !self.constraints.iter().any_(|c| c.break_strain != unlimited)
+    { return 0 }
+
+    let mut broken = ~[];
+
+    for (i, c) in self.constraints.iter().enumerate()
+    {
+      let length = (self.points[c.rb1].position - self.points[c.rb2].position).norm();
+      let strain = if c.rest_length.is_zero() { Zero::zero() } else { (length - c.rest_length) / c.rest_length };
+
+      if strain > c.break_strain || c.impulse.abs() > c.break_impulse
+      { broken.push(i) }
+    }
+
+    let nbroken = broken.len();
+
+    if nbroken == 0
+    { return 0 }
+
+    let mut endpoints = ~[];
+    let mut edges     = HashSet::new();
+
+    // indices are increasing: removing from the end keeps the remaining ones valid
+    while !broken.is_empty()
+    {
+      let c = self.constraints.swap_remove(broken.pop());
+
+      edges.insert(if c.rb1 < c.rb2 { (c.rb1, c.rb2) } else { (c.rb2, c.rb1) });
+
+      for &v in [c.rb1, c.rb2].iter()
+      {
+        if !endpoints.contains(&v)
+        { endpoints.push(v) }
+      }
+    }
+
+    // bending constraints across a broken edge disappear with it
+    self.bendings.retain(|b| {
+      let (e0, e1, _, _) = b.ids;
+
+      !edges.contains(&(if e0 < e1 { (e0, e1) } else { (e1, e0) }))
+    });
+
+    if !self.triangles.is_empty()
+    {
+      for v in endpoints.iter()
+      {
+        if !self.is_pinned(*v)
+        { self.split_vertex(*v) }
+      }
+
+      let params = match self.self_collision
+      {
+        Some(ref sc) => Some((sc.thickness.clone(), sc.cell_size.clone())),
+        None         => None
+      };
+
+      for &(ref thickness, ref cell_size) in params.iter()
+      { self.self_collision = Some(SelfCollision::new(self.triangles, thickness.clone(), cell_size.clone())) }
+    }
+
+    self.projective = None;
+
+    nbroken
+  }
+
+  // Duplicates `v` for each group of its triangles which are no longer linked by a constraint
+  // along one of their edges. The constraints and bending constraints involving `v` follow the
+  // triangles they belong to; the others go to the closest group.
+  fn split_vertex(&mut self, v: uint)
+  {
+    let mut linked = HashSet::new();
+
+    for c in self.constraints.iter()
+    {
+      if c.rb1 == v
+      { linked.insert(c.rb2); }
+      else if c.rb2 == v
+      { linked.insert(c.rb1); }
+    }
+
+    let split = match FanSplit::new(self.triangles, v, &linked, self.points.len())
+    {
+      Some(split) => split,
+      None        => return
+    };
+
+    let positions = self.positions();
+    let sqdist    = |a: uint, b: uint| (positions[a] - positions[b]).sqnorm();
+
+    // the mass is shared between the copies
+    let invmass = self.points[v].invmass * NumCast::from::<N, uint>(split.len());
+    let copy    = PointMass {
+      invmass:       invmass.clone(),
+      damping:       self.points[v].damping.clone(),
+      velocity:      self.points[v].velocity.clone(),
+      position:      self.points[v].position.clone(),
+      last_position: self.points[v].last_position.clone()
+    };
+
+    self.points[v].invmass = invmass;
+
+    for _ in range(1u, split.len())
+    { self.points.push(copy.clone()) }
+
+    for c in self.constraints.mut_iter()
+    {
+      if c.rb1 == v
+      { c.rb1 = split.copy_of(c.rb2, c.rb2, sqdist) }
+      else if c.rb2 == v
+      { c.rb2 = split.copy_of(c.rb1, c.rb1, sqdist) }
+    }
+
+    for b in self.bendings.mut_iter()
+    { b.ids = split.split_bending(b.ids, sqdist) }
+
+    // a membrane element follows its triangle
+    for e in self.membrane.mut_iter()
+    { e.ids = split.split_element(e.ids, sqdist) }
+
+    split.split_triangles(self.triangles);
+  }
+}
@@ -0,0 +1,115 @@
+use std::hashmap::{HashMap, HashSet};
+use nalgebra::vec::Vec3;
+use nalgebra::traits::dot::Dot;
+use nalgebra::traits::cross::Cross;
+use graph::Mesh;
+
+type Vec3f = Vec3<f32>;
+
+/// Tetrahedral mesh of a solid.
+#[deriving(Clone)]
+pub struct TetMesh
+{
+  vbuff: ~[Vec3f],
+  tbuff: ~[(u32, u32, u32, u32)]
+}
+
+impl TetMesh
+{
+  pub fn new(vb: ~[Vec3f], tb: ~[(u32, u32, u32, u32)]) -> TetMesh
+  {
+    TetMesh
+    {
+      vbuff: vb,
+      tbuff: tb
+    }
+  }
+
+  /// The edges of the tetrahedra, each one given once.
+  pub fn edges(&self) -> ~[(u32, u32)]
+  {
+    let mut seen: HashSet<(u32, u32)> = HashSet::new();
+    let mut res = ~[];
+
+    for &(a, b, c, d) in self.tbuff.iter()
+    {
+      for &(e1, e2) in [(a, b), (a, c), (a, d), (b, c), (b, d), (c, d)].iter()
+      {
+        let key = if e1 < e2 { (e1, e2) } else { (e2, e1) };
+
+        if seen.insert(key)
+        { res.push(key) }
+      }
+    }
+
+    res
+  }
+
+  /// The faces belonging to only one tetrahedron, oriented outward.
+  pub fn surface(&self) -> ~[(u32, u32, u32)]
+  {
+    // number of tetrahedra of each face, identified by its sorted vertices
+    let mut counts: HashMap<(u32, u32, u32), uint> = HashMap::new();
+    // each face as given by its first tetrahedron, with the opposite vertex
+    let mut faces = ~[];
+
+    for &(a, b, c, d) in self.tbuff.iter()
+    {
+      for &(f, o) in [((a, b, c), d), ((a, b, d), c), ((a, c, d), b), ((b, c, d), a)].iter()
+      {
+        let count = counts.find_or_insert(sorted(f), 0);
+
+        if *count == 0
+        { faces.push((f, o)) }
+
+        *count = *count + 1;
+      }
+    }
+
+    let mut res = ~[];
+
+    for &((a, b, c), o) in faces.iter()
+    {
+      if *counts.get(&sorted((a, b, c))) != 1
+      { loop }
+
+      let pa     = self.vbuff[a];
+      let normal = (self.vbuff[b] - pa).cross(&(self.vbuff[c] - pa));
+
+      // the opposite vertex is inside
+      if normal.dot(&(self.vbuff[o] - pa)) > 0.0
+      { res.push((a, c, b)) }
+      else
+      { res.push((a, b, c)) }
+    }
+
+    res
+  }
+
+  /// Triangle mesh of the surface, for rendering. It keeps every vertex so that the indices match
+  /// those of the tetrahedra.
+  pub fn surface_mesh(&self) -> Mesh
+  { Mesh::new(self.vbuff.clone(), self.surface()) }
+
+  /// Signed volume of each tetrahedron.
+  pub fn volumes(&self) -> ~[f64]
+  {
+    self.tbuff.iter().transform(|&(a, b, c, d)| {
+      let pa = self.vbuff[a];
+
+      ((self.vbuff[b] - pa).dot(&(self.vbuff[c] - pa).cross(&(self.vbuff[d] - pa))) / 6.0) as f64
+    }).collect()
+  }
+}
+
+fn sorted(f: (u32, u32, u32)) -> (u32, u32, u32)
+{
+  let (a, b, c) = f;
+  let mut v     = [a, b, c];
+
+  if v[0] > v[1] { v.swap(0, 1) }
+  if v[1] > v[2] { v.swap(1, 2) }
+  if v[0] > v[1] { v.swap(0, 1) }
+
+  (v[0], v[1], v[2])
+}
@@ -0,0 +1,231 @@
+use std::vec;
+use std::num::{Zero, One};
+use nalgebra::traits::division_ring::DivisionRing;
+use nalgebra::traits::norm::Norm;
+use nalgebra::traits::dot::Dot;
+use nalgebra::traits::cross::Cross;
+use nalgebra::traits::vector_space::VectorSpace;
+use soft_body::SoftBody;
+use projection::VelocityProjection;
+use projection;
+
+/// Constraint keeping the volume of a tetrahedron at its rest value.
+pub struct VolumeConstraint<N>
+{
+  ids:         (uint, uint, uint, uint),
+  stiffness:   N,
+  compliance:  N,
+  rest_volume: N,
+  impulse:     N
+}
+
+impl<N: DivisionRing + NumCast + Clone> VolumeConstraint<N>
+{
+  /// Creates a constraint whose rest volume is the current volume of the tetrahedron.
+  pub fn new<V: VectorSpace<N> + Dot<N> + Cross<V> + Clone>(
+             ids:       (uint, uint, uint, uint),
+             positions: &[V],
+             stiffness: N) -> VolumeConstraint<N>
+  {
+    let (a, b, c, d) = ids;
+    let (volume, _)  = tetrahedron_volume(&positions[a], &positions[b], &positions[c], &positions[d]);
+
+    VolumeConstraint {
+      ids:         ids,
+      compliance:  if stiffness.is_zero() { Zero::zero() } else { One::one::<N>() / stiffness },
+      stiffness:   stiffness,
+      rest_volume: volume,
+      impulse:     Zero::zero()
+    }
+  }
+
+  pub fn id_list(&self) -> ~[uint]
+  {
+    let (a, b, c, d) = self.ids;
+
+    ~[a, b, c, d]
+  }
+}
+
+/// Signed volume of the tetrahedron `(a, b, c, d)`, with its gradient with respect to `a`, `b`,
+/// `c` and `d`.
+pub fn tetrahedron_volume<N: DivisionRing + NumCast + Clone, V: VectorSpace<N> + Dot<N> + Cross<V> + Clone>(
+                          a: &V, b: &V, c: &V, d: &V) -> (N, ~[V])
+{
+  let sixth = One::one::<N>() / NumCast::from::<N, float>(6.0);
+  let ab    = *b - *a;
+  let ac    = *c - *a;
+  let ad    = *d - *a;
+
+  let g_b = ac.cross(&ad).scalar_mul(&sixth);
+  let g_c = ad.cross(&ab).scalar_mul(&sixth);
+  let g_d = ab.cross(&ac).scalar_mul(&sixth);
+  let g_a = -(g_b + g_c + g_d);
+
+  (ab.dot(&g_b), ~[g_a, g_b, g_c, g_d])
+}
+
+/// Constraint on the volume enclosed by the triangles of a closed surface: the volume is driven
+/// toward `pressure` times its rest value.
+pub struct InflationConstraint<N>
+{
+  stiffness:   N,
+  compliance:  N,
+  rest_volume: N,
+  /// Ratio of the target volume to the rest volume.
+  pressure:    N,
+  impulse:     N
+}
+
+impl<N: DivisionRing + Clone> InflationConstraint<N>
+{
+  pub fn target_volume(&self) -> N
+  { self.rest_volume * self.pressure }
+}
+
+/// Signed volume enclosed by the triangles `triangles`, with its gradient with respect to each
+/// point. The triangles must be oriented consistently; the volume is negative if their normals
+/// point inward.
+pub fn enclosed_volume<N: DivisionRing + NumCast + Clone, V: VectorSpace<N> + Dot<N> + Cross<V> + Clone>(
+                       positions: &[V],
+                       triangles: &[(uint, uint, uint)]) -> (N, ~[V])
+{
+  let sixth      = One::one::<N>() / NumCast::from::<N, float>(6.0);
+  let mut volume = Zero::zero::<N>();
+  let mut grads  = vec::from_elem(positions.len(), Zero::zero::<V>());
+
+  for &(a, b, c) in triangles.iter()
+  {
+    let (pa, pb, pc) = (&positions[a], &positions[b], &positions[c]);
+    let bc           = pb.cross(pc);
+
+    volume   = volume + pa.dot(&bc);
+    grads[a] = grads[a] + bc.scalar_mul(&sixth);
+    grads[b] = grads[b] + pc.cross(pa).scalar_mul(&sixth);
+    grads[c] = grads[c] + pa.cross(pb).scalar_mul(&sixth);
+  }
+
+  (volume * sixth, grads)
+}
+
+impl<N: DivisionRing + NumCast + Signed + Orderable + Bounded + Round + Trigonometric + Eq + Ord + Clone,
+     V: VectorSpace<N> + Norm<N> + Dot<N> + Cross<V> + Clone>
+    SoftBody<N, V>
+{
+  /// Adds volume preservation constraints on tetrahedra, with their current volume as rest
+  /// volume.
+  pub fn add_volume_constraints(&mut self, tetrahedra: &[(uint, uint, uint, uint)], stiffness: &[N])
+  {
+    assert!(tetrahedra.len() == stiffness.len(), "Each tetrahedron must have a stiffness.");
+
+    let positions = self.positions();
+
+    for (t, s) in tetrahedra.iter().zip(stiffness.iter())
+    { self.volumes.push(VolumeConstraint::new(*t, positions, s.clone())) }
+  }
+
+  /// Keeps the volume enclosed by the triangles at `pressure` times its current value. The
+  /// triangles must have been set, and form a closed surface.
+  pub fn inflate(&mut self, pressure: N, stiffness: N)
+  {
+    assert!(!self.triangles.is_empty(), "Inflation requires the triangles of the surface.");
+
+    let (volume, _) = enclosed_volume(self.positions(), self.triangles);
+
+    self.inflation = Some(InflationConstraint {
+      compliance:  if stiffness.is_zero() { Zero::zero() } else { One::one::<N>() / stiffness },
+      stiffness:   stiffness,
+      rest_volume: volume,
+      pressure:    pressure,
+      impulse:     Zero::zero()
+    })
+  }
+
+  /// Changes the ratio of the target volume to the rest volume of an inflated body.
+  pub fn set_pressure(&mut self, pressure: N)
+  {
+    match self.inflation
+    {
+      Some(ref mut i) => i.pressure = pressure,
+      None            => fail!("The body is not inflated.")
+    }
+  }
+
+  pub fn deflate(&mut self)
+  { self.inflation = None }
+
+  /// Volume currently enclosed by the triangles.
+  pub fn enclosed_volume(&self) -> N
+  {
+    let (volume, _) = enclosed_volume(self.positions(), self.triangles);
+
+    volume
+  }
+
+  /// Volume constraints of the tetrahedra and inflation constraint, to be resolved at the
+  /// velocity level by `projection::sweep_velocities`.
+  pub fn collect_volume_projections(&self, out: &mut ~[VelocityProjection<N, V>])
+  {
+    for v in self.volumes.iter()
+    {
+      if v.stiffness.is_zero()
+      { loop }
+
+      let (a, b, c, d)    = v.ids;
+      let (volume, grads) = tetrahedron_volume(&self.points[a].position, &self.points[b].position,
+                                               &self.points[c].position, &self.points[d].position);
+
+      out.push(VelocityProjection {
+        ids:       v.id_list(),
+        grads:     grads,
+        value:     volume - v.rest_volume,
+        stiffness: v.stiffness.clone()
+      })
+    }
+
+    for inflation in self.inflation.iter()
+    {
+      if inflation.stiffness.is_zero()
+      { loop }
+
+      let (volume, grads) = enclosed_volume(self.positions(), self.triangles);
+
+      out.push(VelocityProjection {
+        ids:       range(0u, self.points.len()).collect(),
+        grads:     grads,
+        value:     volume - inflation.target_volume(),
+        stiffness: inflation.stiffness.clone()
+      })
+    }
+  }
+
+  pub fn solve_volume_positions(&mut self, dt2: &N)
+  {
+    for v in self.volumes.mut_iter()
+    {
+      if v.stiffness.is_zero()
+      { loop }
+
+      let (a, b, c, d) = v.ids;
+      let (volume, grads) = tetrahedron_volume(&self.points[a].position, &self.points[b].position,
+                                               &self.points[c].position, &self.points[d].position);
+
+      projection::project_positions(self.points, v.id_list(), grads, &(volume - v.rest_volume),
+                                    &(v.compliance / *dt2), &mut v.impulse)
+    }
+
+    if self.inflation.is_some()
+    {
+      let (volume, grads) = enclosed_volume(self.positions(), self.triangles);
+      let ids: ~[uint]    = range(0u, self.points.len()).collect();
+
+      match self.inflation
+      {
+        Some(ref mut inflation) if !inflation.stiffness.is_zero() =>
+          projection::project_positions(self.points, ids, grads, &(volume - inflation.target_volume()),
+                                        &(inflation.compliance / *dt2), &mut inflation.impulse),
+        _ => { }
+      }
+    }
+  }
+}
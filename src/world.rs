@@ -0,0 +1,356 @@
+use std::vec;
+use std::num::Zero;
+use nalgebra::traits::division_ring::DivisionRing;
+use nalgebra::traits::norm::Norm;
+use nalgebra::traits::dot::Dot;
+use nalgebra::traits::cross::Cross;
+use nalgebra::traits::dim::Dim;
+use nalgebra::traits::indexable::Indexable;
+use nalgebra::traits::vector_space::VectorSpace;
+use soft_body::{SoftBody, PointMass, PGSSolver, gauss_seidel_solve};
+use collision::Collider;
+use self_collision::{SelfCollision, SelfContact};
+use self_collision;
+use projection;
+use force_field::ForceField;
+use attachment::Stitches;
+use stepper::Stepper;
+
+/// A scene of several soft bodies sharing force fields, colliders and a stepper.
+///
+/// At each substep, the springs, contacts, rigid body couplings, bending and volume constraints
+/// and self-collisions of every body, the collisions between the bodies and the stitches are
+/// resolved together in a single projected Gauss-Seidel solve (see `gauss_seidel_solve`). The
+/// bodies must use the `PGSSolver`; their steppers are not used.
+pub struct World<N, V>
+{
+  bodies:       ~[@mut SoftBody<N, V>],
+  /// Force fields applied to every body, in addition to their own.
+  force_fields: ~[@ForceField<N, V>],
+  /// Obstacles for every body, in addition to their own.
+  colliders:    ~[Collider<N, V>],
+  /// Stitches between the bodies of the given indices.
+  stitches:     ~[(uint, uint, Stitches<N>)],
+  /// Uniform acceleration.
+  gravity:      V,
+  /// Number of Gauss-Seidel iterations: the largest number of iterations of the bodies, unless
+  /// set afterwards.
+  iterations:   uint,
+  stepper:      Stepper<N>,
+  time:         N,
+  /// Minimal distance between the surfaces of different bodies, if they collide.
+  collision_thickness: Option<N>,
+  priv previous_positions: ~[~[V]]
+}
+
+impl<V: VectorSpace<N> + Dot<N> + Norm<N> + Cross<V> + Indexable<uint, N> + Dim + Clone + ToStr,
+     N:  DivisionRing + Orderable + NumCast + Signed + Bounded + Round + Trigonometric + Ord + ToStr + Eq + Clone>
+     World<N, V>
+{
+  pub fn new(gravity: V, stepper: Stepper<N>) -> World<N, V>
+  {
+    World {
+      bodies:       ~[],
+      force_fields: ~[],
+      colliders:    ~[],
+      stitches:     ~[],
+      gravity:      gravity,
+      iterations:   0,
+      stepper:      stepper,
+      time:         Zero::zero(),
+      collision_thickness: None,
+      previous_positions:  ~[]
+    }
+  }
+
+  /// Adds a body and returns its index.
+  pub fn add_body(&mut self, body: @mut SoftBody<N, V>) -> uint
+  {
+    assert!(body.solver == PGSSolver, "The bodies of a world are solved with projected Gauss-Seidel.");
+
+    self.iterations = self.iterations.max(&body.iterations);
+    self.bodies.push(body);
+    self.bodies.len() - 1
+  }
+
+  /// Removes a body and the stitches involving it. The indices of the next bodies are
+  /// decremented.
+  pub fn remove_body(&mut self, i: uint) -> @mut SoftBody<N, V>
+  {
+    self.stitches.retain(|&(b1, b2, _)| b1 != i && b2 != i);
+
+    for s in self.stitches.mut_iter()
+    {
+      match *s
+      {
+        (ref mut b1, ref mut b2, _) =>
+        {
+          if *b1 > i { *b1 = *b1 - 1 }
+          if *b2 > i { *b2 = *b2 - 1 }
+        }
+      }
+    }
+
+    self.previous_positions = ~[];
+
+    self.bodies.remove(i)
+  }
+
+  pub fn body(&self, i: uint) -> @mut SoftBody<N, V>
+  { self.bodies[i] }
+
+  pub fn nbodies(&self) -> uint
+  { self.bodies.len() }
+
+  /// Adds a force field applied to every body and returns its index.
+  pub fn add_force_field(&mut self, field: @ForceField<N, V>) -> uint
+  {
+    self.force_fields.push(field);
+    self.force_fields.len() - 1
+  }
+
+  pub fn remove_force_field(&mut self, i: uint) -> @ForceField<N, V>
+  { self.force_fields.remove(i) }
+
+  /// Adds an obstacle for every body and returns its index.
+  pub fn add_collider(&mut self, collider: Collider<N, V>) -> uint
+  {
+    self.colliders.push(collider);
+    self.colliders.len() - 1
+  }
+
+  pub fn remove_collider(&mut self, i: uint) -> Collider<N, V>
+  { self.colliders.remove(i) }
+
+  /// Stitches the body `b1` to the body `b2`. Returns the index of the stitches.
+  pub fn add_stitches(&mut self, b1: uint, b2: uint, stitches: Stitches<N>) -> uint
+  {
+    assert!(b1 != b2, "A body cannot be stitched to itself: use its constraints instead.");
+
+    self.stitches.push((b1, b2, stitches));
+    self.stitches.len() - 1
+  }
+
+  pub fn remove_stitches(&mut self, i: uint) -> (uint, uint, Stitches<N>)
+  { self.stitches.remove(i) }
+
+  /// Makes the surfaces of different bodies, and of each body with itself, stay `thickness`
+  /// apart. The triangles of the bodies must have been set. This replaces the self-collisions
+  /// of the bodies.
+  pub fn enable_collisions(&mut self, thickness: N)
+  { self.collision_thickness = Some(thickness) }
+
+  pub fn disable_collisions(&mut self)
+  { self.collision_thickness = None }
+
+  /// Positions of the body `i` to render, interpolated between the last two steps if the
+  /// stepper says so.
+  pub fn interpolated_positions(&self, i: uint) -> ~[V]
+  {
+    let body = self.bodies[i];
+
+    if !self.stepper.interpolate || self.previous_positions.len() != self.bodies.len() ||
+       self.previous_positions[i].len() != body.points.len()
+    { return body.positions() }
+
+    let alpha = self.stepper.alpha();
+
+    body.points.iter().zip(self.previous_positions[i].iter()).transform(|(p, prev)| {
+      *prev + (p.position - *prev).scalar_mul(&alpha)
+    }).collect()
+  }
+
+  /// Advances the simulation by a wall-clock `delta`, running as many fixed steps as the stepper
+  /// accumulated. Returns the number of steps run.
+  pub fn step(&mut self, delta: N) -> uint
+  {
+    let nsteps = self.stepper.advance(delta);
+    let dt     = self.stepper.substep_dt();
+
+    for _ in range(0u, nsteps)
+    {
+      self.previous_positions = self.bodies.iter().transform(|b| b.positions()).collect();
+
+      for _ in range(0u, self.stepper.substeps)
+      {
+        self.time = self.time + dt;
+
+        for c in self.colliders.mut_iter()
+        { c.update(self.time.clone(), &dt) }
+
+        for body in self.bodies.iter()
+        { body.integrate_with_fields(&dt, &self.gravity, self.force_fields) }
+
+        self.solve(dt.clone());
+
+        for body in self.bodies.iter()
+        { body.finish_substep(&dt) }
+      }
+    }
+
+    nsteps
+  }
+
+  /// Resolves the constraints and the collisions of all the bodies, and the stitches, together.
+  pub fn solve(&mut self, dt: N)
+  {
+    let fext_dt = self.gravity.scalar_mul(&dt);
+
+    // the points of all the bodies, then their rigid bodies
+    let mut points        = ~[];
+    let mut point_offsets = ~[];
+    let mut rigid_offsets = ~[];
+    let mut nrigids       = 0u;
+
+    for body in self.bodies.iter()
+    {
+      point_offsets.push(points.len());
+      rigid_offsets.push(nrigids);
+
+      points.push_all(body.points);
+      nrigids = nrigids + body.rigid_bodies.len();
+    }
+
+    let npoints         = points.len();
+    let mut constraints = ~[];
+    let mut friction    = ~[];
+    let mut springs     = ~[];
+    let mut contacts    = ~[];
+    let mut projections = ~[];
+
+    for (b, body) in self.bodies.iter().enumerate()
+    {
+      let mut cs = ~[];
+      let mut fs = ~[];
+
+      body.collect_constraints(dt.clone(), &mut cs, false);
+
+      springs.push(constraints.len());
+
+      body.collect_contacts(dt.clone(), &mut cs, &mut fs);
+      body.collect_collider_contacts(self.colliders, dt.clone(), &mut cs, &mut fs);
+      body.collect_rigid_constraints(dt.clone(), &mut cs, &mut fs);
+
+      if self.collision_thickness.is_none()
+      {
+        let mut scs = ~[];
+
+        for sc in body.self_collision.iter()
+        { sc.collect_contacts(body.points, body.triangles, &dt, &mut scs) }
+
+        for c in scs.mut_iter()
+        {
+          for id in c.ids.mut_iter()
+          { *id = *id + point_offsets[b] }
+        }
+
+        contacts.push_all_move(scs);
+      }
+
+      let mut ps = ~[];
+
+      body.collect_projections(&mut ps);
+
+      for p in ps.mut_iter()
+      {
+        for id in p.ids.mut_iter()
+        { *id = *id + point_offsets[b] }
+      }
+
+      projections.push_all_move(ps);
+
+      let nlocal = body.points.len();
+      let remap  = |id: int| -> int {
+        if id < 0                    { id }
+        else if (id as uint) < nlocal { (point_offsets[b] + id as uint) as int }
+        else                         { (npoints + rigid_offsets[b] + id as uint - nlocal) as int }
+      };
+
+      for f in fs.mut_iter()
+      {
+        f.id1 = remap(f.id1);
+        f.id2 = remap(f.id2);
+        f.friction_limit_id = f.friction_limit_id + constraints.len();
+      }
+
+      for c in cs.mut_iter()
+      {
+        c.id1 = remap(c.id1);
+        c.id2 = remap(c.id2);
+      }
+
+      constraints.push_all_move(cs);
+      friction.push_all_move(fs);
+    }
+
+    for thickness in self.collision_thickness.iter()
+    { self.collect_collisions(points, thickness, &dt, &mut contacts) }
+
+    let mut lambdas: ~[~[V]] = self.stitches.iter().transform(|&(_, _, ref s)| {
+      vec::from_elem(s.len(), Zero::zero::<V>())
+    }).collect();
+
+    let res = do gauss_seidel_solve(points, constraints, friction, nrigids, self.iterations) |points| {
+      projection::sweep_velocities(points, projections, &dt, &fext_dt);
+
+      for (&(b1, b2, ref stitches), ls) in self.stitches.iter().zip(lambdas.mut_iter())
+      { stitches.solve_velocities(points, point_offsets[b1], point_offsets[b2], &dt, &fext_dt, *ls) }
+
+      self_collision::solve_contacts(points, contacts, &fext_dt)
+    };
+
+    for (b, body) in self.bodies.iter().enumerate()
+    {
+      for (i, p) in body.points.mut_iter().enumerate()
+      { p.velocity = points[point_offsets[b] + i].velocity.clone() }
+
+      for i in range(0u, body.constraints.len())
+      { body.constraints[i].impulse = constraints[springs[b] + i].impulse.clone() }
+
+      for (k, rb) in body.rigid_bodies.iter().enumerate()
+      {
+        let (ref lv, ref av) = res[rigid_offsets[b] + k];
+
+        rb.apply_velocity_change(lv, av)
+      }
+    }
+  }
+
+  // Contacts between the surfaces of all the bodies, seen as a single surface. `points` are the
+  // points of all the bodies, numbered body after body.
+  fn collect_collisions(&self,
+                        points:    &[PointMass<N, V>],
+                        thickness: &N,
+                        dt:        &N,
+                        out:       &mut ~[SelfContact<N, V>])
+  {
+    let mut triangles   = ~[];
+    let mut mean_length = Zero::zero::<N>();
+    let mut nlengths    = 0u;
+    let mut offset      = 0u;
+
+    for body in self.bodies.iter()
+    {
+      assert!(!body.triangles.is_empty(), "Collisions require the triangles of the surfaces.");
+
+      for &(a, b, c) in body.triangles.iter()
+      { triangles.push((a + offset, b + offset, c + offset)) }
+
+      for c in body.constraints.iter()
+      { mean_length = mean_length + c.rest_length }
+
+      nlengths = nlengths + body.constraints.len();
+      offset   = offset + body.points.len();
+    }
+
+    if nlengths != 0
+    { mean_length = mean_length / NumCast::from::<N, uint>(nlengths) }
+
+    // same cell size as `SoftBody::enable_self_collision`
+    let two: N    = NumCast::from::<N, float>(2.0);
+    let cell_size = mean_length.max(&(*thickness * two));
+    let collision = SelfCollision::new(triangles, thickness.clone(), cell_size);
+
+    collision.collect_contacts(points, triangles, dt, out)
+  }
+}
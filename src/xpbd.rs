@@ -0,0 +1,110 @@
+use std::num::Zero;
+use nalgebra::traits::division_ring::DivisionRing;
+use nalgebra::traits::norm::Norm;
+use nalgebra::traits::dot::Dot;
+use nalgebra::traits::cross::Cross;
+use nalgebra::traits::dim::Dim;
+use nalgebra::traits::indexable::Indexable;
+use nalgebra::traits::vector_space::VectorSpace;
+use soft_body::SoftBody;
+
+impl<V: VectorSpace<N> + Dot<N> + Norm<N> + Cross<V> + Indexable<uint, N> + Dim + Clone + ToStr,
+     N:  DivisionRing + Orderable + NumCast + Signed + Bounded + Round + Trigonometric + Ord + ToStr + Eq + Clone>
+     SoftBody<N, V>
+{
+  /// Projects the constraints on the positions predicted by `integrate`, then deduces the
+  /// velocities from the displacement of the points.
+  ///
+  /// Self-collisions and rigid bodies are only handled by the velocity-level solvers.
+  pub fn solve_xpbd(&mut self, dt: N)
+  {
+    assert!(self.rigid_bodies.is_empty(), "Rigid bodies require a velocity-level solver.");
+
+    let dt2 = dt * dt;
+
+    // the Lagrange multipliers are accumulated from scratch at each step
+    for c in self.constraints.mut_iter()
+    { c.impulse = Zero::zero() }
+
+    for b in self.bendings.mut_iter()
+    { b.impulse = Zero::zero() }
+
+    for v in self.volumes.mut_iter()
+    { v.impulse = Zero::zero() }
+
+    for i in self.inflation.mut_iter()
+    { i.impulse = Zero::zero() }
+
+    for _ in range(0u, self.iterations)
+    {
+      for c in self.constraints.mut_iter()
+      {
+        if c.stiffness.is_zero()
+        { loop }
+
+        let w1    = self.points[c.rb1].invmass.clone();
+        let w2    = self.points[c.rb2].invmass.clone();
+        let alpha = c.compliance / dt2;
+        let denom = w1 + w2 + alpha;
+
+        if (w1 + w2).is_zero()
+        { loop }
+
+        let mut normal = self.points[c.rb1].position - self.points[c.rb2].position;
+        let     length = normal.normalize();
+
+        if length.is_zero()
+        { loop }
+
+        let dlambda = (c.rest_length - length - alpha * c.impulse) / denom;
+
+        c.impulse = c.impulse + dlambda;
+
+        self.points[c.rb1].position = self.points[c.rb1].position + normal.scalar_mul(&(w1 * dlambda));
+        self.points[c.rb2].position = self.points[c.rb2].position - normal.scalar_mul(&(w2 * dlambda));
+      }
+
+      self.solve_bending_positions(&dt2);
+      self.solve_volume_positions(&dt2);
+      self.project_contacts(&dt);
+    }
+
+    for p in self.points.mut_iter()
+    {
+      if !p.invmass.is_zero()
+      { p.velocity = (p.position - p.last_position).scalar_div(&dt) }
+    }
+  }
+
+  /// Moves the points out of the colliders, with Coulomb friction on their displacement.
+  pub fn project_contacts(&mut self, dt: &N)
+  {
+    for collider in self.colliders.iter()
+    {
+      for p in self.points.mut_iter()
+      {
+        if p.invmass.is_zero()
+        { loop }
+
+        match collider.contact(&p.position, &Zero::zero())
+        {
+          Some((n, dist)) =>
+          {
+            p.position = p.position - n.scalar_mul(&dist);
+
+            let disp    = p.position - p.last_position - collider.velocity.scalar_mul(dt);
+            let tangent = disp - n.scalar_mul(&disp.dot(&n));
+            let slip    = tangent.norm();
+            let limit   = -dist * collider.friction;
+
+            if slip <= limit
+            { p.position = p.position - tangent }
+            else
+            { p.position = p.position - tangent.scalar_mul(&(limit / slip)) }
+          },
+          None => { }
+        }
+      }
+    }
+  }
+}